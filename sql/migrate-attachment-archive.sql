@@ -0,0 +1,57 @@
+-- One-off migration for databases created before the attachments of cached messages were archived.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS attachment_archive_enabled bool NOT NULL DEFAULT true,
+	ADD COLUMN IF NOT EXISTS attachment_archive_max_size int8 NOT NULL DEFAULT 8388608,
+	ADD COLUMN IF NOT EXISTS attachment_archive_max_age int8 NULL DEFAULT 604800,
+	ADD COLUMN IF NOT EXISTS attachment_archive_budget int8 NULL DEFAULT 1073741824;
+
+CREATE TABLE IF NOT EXISTS ttc_attachment_archive (
+	id serial4 NOT NULL,
+	message_id int8 NOT NULL,
+	filename varchar(1024) NOT NULL,
+	"path" varchar(4096) NOT NULL,
+	"size" int8 NOT NULL,
+	archived_at timestamptz NOT NULL,
+	CONSTRAINT ttc_attachment_archive_pk PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS ttc_attachment_archive_message_id_idx ON ttc_attachment_archive USING btree (message_id);
+CREATE INDEX IF NOT EXISTS ttc_attachment_archive_archived_at_idx ON ttc_attachment_archive USING btree (archived_at);
+
+-- The config view selects every config property, so it is recreated with the new ones
+DROP VIEW IF EXISTS ttc_config_view;
+CREATE OR REPLACE VIEW public.ttc_config_view
+AS SELECT tc.id AS config_id,
+    tcp.id AS config_properties_id,
+    tcp.support_channel AS support_channel,
+    tcp.welcome_channel AS welcome_channel,
+    tcp.verified_role AS verified_role,
+    tcp.moderator_role AS moderator_role,
+    tcp.verification_min_account_age AS verification_min_account_age,
+    tcp.verification_manual_approval AS verification_manual_approval,
+    tcp.captcha_enabled AS captcha_enabled,
+    tcp.captcha_attempts AS captcha_attempts,
+    tcp.captcha_timeout AS captcha_timeout,
+    tcp.verification_request_channel AS verification_request_channel,
+    tcp.onboarding_review_channel AS onboarding_review_channel,
+    tcp.welcome_card_enabled AS welcome_card_enabled,
+    tcp.role_memory_restore_all AS role_memory_restore_all,
+    tcp.message_cache_max_age AS message_cache_max_age,
+    tcp.message_cache_max_rows AS message_cache_max_rows,
+    tcp.attachment_archive_enabled AS attachment_archive_enabled,
+    tcp.attachment_archive_max_size AS attachment_archive_max_size,
+    tcp.attachment_archive_max_age AS attachment_archive_max_age,
+    tcp.attachment_archive_budget AS attachment_archive_budget,
+    tcc.channel_id AS conveyance_channel,
+    the.name AS harold_emoji,
+    twm.welcome_message AS welcome_message
+   FROM ttc_config tc
+     FULL JOIN ttc_config_properties tcp ON tc.config_properties_id = tcp.id
+     FULL JOIN ttc_conveyance_channel tcc ON tc.conveyance_id = tcc.id
+     FULL JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
+     FULL JOIN ttc_welcome_message twm ON tc.welcome_message_id = twm.id;
+
+
+COMMIT;
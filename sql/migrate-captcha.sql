@@ -0,0 +1,10 @@
+-- One-off migration for databases created before the verification had an optional captcha step.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS captcha_enabled bool NOT NULL DEFAULT false,
+	ADD COLUMN IF NOT EXISTS captcha_attempts int4 NOT NULL DEFAULT 3,
+	ADD COLUMN IF NOT EXISTS captcha_timeout int8 NOT NULL DEFAULT 300;
+
+COMMIT;
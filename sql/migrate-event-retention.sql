@@ -0,0 +1,45 @@
+-- One-off migration for databases created before stored events were removed after a retention.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS event_max_age int8 NULL DEFAULT 31536000;
+
+-- The config view selects every config property, so it is recreated with the new ones
+DROP VIEW IF EXISTS ttc_config_view;
+CREATE OR REPLACE VIEW public.ttc_config_view
+AS SELECT tc.id AS config_id,
+    tcp.id AS config_properties_id,
+    tcp.support_channel AS support_channel,
+    tcp.welcome_channel AS welcome_channel,
+    tcp.verified_role AS verified_role,
+    tcp.moderator_role AS moderator_role,
+    tcp.verification_min_account_age AS verification_min_account_age,
+    tcp.verification_manual_approval AS verification_manual_approval,
+    tcp.captcha_enabled AS captcha_enabled,
+    tcp.captcha_attempts AS captcha_attempts,
+    tcp.captcha_timeout AS captcha_timeout,
+    tcp.verification_request_channel AS verification_request_channel,
+    tcp.onboarding_review_channel AS onboarding_review_channel,
+    tcp.welcome_card_enabled AS welcome_card_enabled,
+    tcp.role_memory_restore_all AS role_memory_restore_all,
+    tcp.message_cache_max_age AS message_cache_max_age,
+    tcp.message_cache_max_rows AS message_cache_max_rows,
+    tcp.attachment_archive_enabled AS attachment_archive_enabled,
+    tcp.attachment_archive_max_size AS attachment_archive_max_size,
+    tcp.attachment_archive_max_age AS attachment_archive_max_age,
+    tcp.attachment_archive_budget AS attachment_archive_budget,
+    tcp.impersonation_alert_threshold AS impersonation_alert_threshold,
+    tcp.ghost_ping_window AS ghost_ping_window,
+    tcp.event_max_age AS event_max_age,
+    tcc.channel_id AS conveyance_channel,
+    the.name AS harold_emoji,
+    twm.welcome_message AS welcome_message
+   FROM ttc_config tc
+     FULL JOIN ttc_config_properties tcp ON tc.config_properties_id = tcp.id
+     FULL JOIN ttc_conveyance_channel tcc ON tc.conveyance_id = tcc.id
+     FULL JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
+     FULL JOIN ttc_welcome_message twm ON tc.welcome_message_id = twm.id;
+
+
+COMMIT;
@@ -0,0 +1,16 @@
+-- One-off migration for databases created before the conveyance events were stored.
+
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS ttc_events (
+	id bigserial NOT NULL,
+	guild_id int8 NULL,
+	user_id int8 NOT NULL,
+	event_type varchar(32) NOT NULL,
+	event_time timestamptz NOT NULL,
+	payload jsonb NOT NULL,
+	CONSTRAINT ttc_events_pk PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS ttc_events_user_id_event_time_idx ON ttc_events USING btree (user_id, event_time);
+
+COMMIT;
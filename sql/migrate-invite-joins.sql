@@ -0,0 +1,18 @@
+-- One-off migration for databases created before the invites new members joined with were tracked.
+
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS ttc_invite_joins (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	inviter_id int8 NULL,
+	invite_code varchar(32) NULL,
+	joined_at timestamptz NOT NULL,
+	left_at timestamptz NULL,
+	CONSTRAINT ttc_invite_joins_pk PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS ttc_invite_joins_inviter_id_idx ON ttc_invite_joins USING btree (guild_id, inviter_id);
+CREATE INDEX IF NOT EXISTS ttc_invite_joins_user_id_idx ON ttc_invite_joins USING btree (guild_id, user_id);
+
+COMMIT;
@@ -0,0 +1,84 @@
+-- One-off migration for databases created before the log categories were introduced.
+-- The old conveyance blacklist only applied to message logs, so its channels are copied into
+-- the blacklist of the messages category before the old table is dropped.
+
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS ttc_log_categories (
+	category varchar(32) NOT NULL,
+	enabled bool NOT NULL DEFAULT true,
+	CONSTRAINT ttc_log_categories_pk PRIMARY KEY (category)
+);
+
+INSERT INTO ttc_log_categories (category) VALUES
+	('messages'),
+	('member_join_leave'),
+	('member_updates'),
+	('bans'),
+	('moderation'),
+	('automod'),
+	('voice'),
+	('server_updates')
+ON CONFLICT (category) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS ttc_log_channels (
+	id serial4 NOT NULL,
+	category varchar(32) NOT NULL,
+	channel_id int8 NOT NULL,
+	CONSTRAINT ttc_log_channels_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_log_channels_un UNIQUE (category, channel_id),
+	CONSTRAINT fk_log_channels_category FOREIGN KEY (category) REFERENCES ttc_log_categories(category) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS ttc_log_blacklist_channels (
+	id serial4 NOT NULL,
+	category varchar(32) NOT NULL,
+	channel_id int8 NOT NULL,
+	CONSTRAINT ttc_log_blacklist_channels_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_log_blacklist_channels_un UNIQUE (category, channel_id),
+	CONSTRAINT fk_log_blacklist_channels_category FOREIGN KEY (category) REFERENCES ttc_log_categories(category) ON DELETE CASCADE
+);
+
+INSERT INTO ttc_log_blacklist_channels (category, channel_id)
+SELECT DISTINCT 'messages', channel_id FROM ttc_conveyance_blacklist_channel
+ON CONFLICT (category, channel_id) DO NOTHING;
+
+-- The config view joins the old table, so it goes before the table and is recreated below
+DROP VIEW IF EXISTS ttc_config_view;
+ALTER TABLE public.ttc_config DROP CONSTRAINT IF EXISTS fk_config_conveyance_blacklist;
+ALTER TABLE public.ttc_config DROP COLUMN IF EXISTS conveyance_blacklist_id;
+DROP TABLE IF EXISTS ttc_conveyance_blacklist_channel;
+
+-- Verification requests got their own channel setting together with the categories. The recreated
+-- view also selects the properties added by the migrations that come before this one.
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS verification_request_channel int8 NULL;
+
+CREATE OR REPLACE VIEW public.ttc_config_view
+AS SELECT tc.id AS config_id,
+    tcp.id AS config_properties_id,
+    tcp.support_channel AS support_channel,
+    tcp.welcome_channel AS welcome_channel,
+    tcp.verified_role AS verified_role,
+    tcp.moderator_role AS moderator_role,
+    tcp.verification_min_account_age AS verification_min_account_age,
+    tcp.verification_manual_approval AS verification_manual_approval,
+    tcp.captcha_enabled AS captcha_enabled,
+    tcp.captcha_attempts AS captcha_attempts,
+    tcp.captcha_timeout AS captcha_timeout,
+    tcp.verification_request_channel AS verification_request_channel,
+    tcp.onboarding_review_channel AS onboarding_review_channel,
+    tcp.welcome_card_enabled AS welcome_card_enabled,
+    tcp.role_memory_restore_all AS role_memory_restore_all,
+    tcp.message_cache_max_age AS message_cache_max_age,
+    tcp.message_cache_max_rows AS message_cache_max_rows,
+    tcc.channel_id AS conveyance_channel,
+    the.name AS harold_emoji,
+    twm.welcome_message AS welcome_message
+   FROM ttc_config tc
+     FULL JOIN ttc_config_properties tcp ON tc.config_properties_id = tcp.id
+     FULL JOIN ttc_conveyance_channel tcc ON tc.conveyance_id = tcc.id
+     FULL JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
+     FULL JOIN ttc_welcome_message twm ON tc.welcome_message_id = twm.id;
+
+COMMIT;
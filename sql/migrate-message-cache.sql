@@ -0,0 +1,40 @@
+-- One-off migration for databases created before the message cache was keyed by message id.
+-- The old cache was a ring of 500 prefilled rows, the rows that were never used are removed and
+-- the messages in the others are kept.
+
+BEGIN;
+
+DELETE FROM ttc_message_cache
+WHERE message_id IS NULL OR channel_id IS NULL OR user_id IS NULL OR message_time IS NULL;
+
+-- A message can be in the ring twice if it was written again, only the newest copy is kept
+DELETE FROM ttc_message_cache old_copy USING ttc_message_cache new_copy
+WHERE old_copy.message_id = new_copy.message_id AND old_copy.id < new_copy.id;
+
+UPDATE ttc_message_cache SET "content" = '' WHERE "content" IS NULL;
+UPDATE ttc_message_cache SET attachments = '' WHERE attachments IS NULL;
+
+ALTER TABLE ttc_message_cache DROP CONSTRAINT ttc_message_cache_pkey;
+ALTER TABLE ttc_message_cache DROP COLUMN id;
+ALTER TABLE ttc_message_cache
+	ALTER COLUMN message_id SET NOT NULL,
+	ALTER COLUMN channel_id SET NOT NULL,
+	ALTER COLUMN user_id SET NOT NULL,
+	ALTER COLUMN message_time SET NOT NULL,
+	ALTER COLUMN "content" TYPE text,
+	ALTER COLUMN "content" SET NOT NULL,
+	ALTER COLUMN attachments TYPE text,
+	ALTER COLUMN attachments SET NOT NULL,
+	ADD CONSTRAINT ttc_message_cache_pkey PRIMARY KEY (message_id);
+
+CREATE INDEX IF NOT EXISTS ttc_message_cache_message_time_idx ON ttc_message_cache USING btree (message_time);
+
+-- The cache is limited by age and size instead of the number of rows in the ring
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS message_cache_max_age int8 NULL DEFAULT 1209600,
+	ADD COLUMN IF NOT EXISTS message_cache_max_rows int8 NULL;
+
+-- The position in the ring isn't needed anymore
+DROP TABLE IF EXISTS ttc_conveyance_state;
+
+COMMIT;
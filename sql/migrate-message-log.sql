@@ -0,0 +1,12 @@
+-- One-off migration for databases created before moderators could search the cached messages.
+
+BEGIN;
+
+-- Edited and deleted messages stay in the cache and can be filtered on
+ALTER TABLE ttc_message_cache
+	ADD COLUMN IF NOT EXISTS edited_at timestamptz NULL,
+	ADD COLUMN IF NOT EXISTS deleted_at timestamptz NULL;
+
+CREATE INDEX IF NOT EXISTS ttc_message_cache_user_id_idx ON ttc_message_cache USING btree (user_id, message_time);
+
+COMMIT;
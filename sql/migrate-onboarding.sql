@@ -0,0 +1,38 @@
+-- One-off migration for databases created before the verification had the rules and questions
+-- onboarding step.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS onboarding_review_channel int8 NULL;
+
+CREATE TABLE IF NOT EXISTS ttc_onboarding_rules (
+	id serial4 NOT NULL,
+	page_order int4 NOT NULL,
+	title varchar(256) NOT NULL,
+	"content" varchar(4000) NOT NULL,
+	CONSTRAINT ttc_onboarding_rules_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS ttc_onboarding_questions (
+	id serial4 NOT NULL,
+	question_order int4 NOT NULL,
+	question varchar(45) NOT NULL,
+	placeholder varchar(100) NULL,
+	required bool NOT NULL DEFAULT true,
+	CONSTRAINT ttc_onboarding_questions_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS ttc_onboarding_answers (
+	id serial4 NOT NULL,
+	user_id int8 NOT NULL,
+	question varchar(45) NOT NULL,
+	answer varchar(1024) NOT NULL,
+	answer_time timestamptz NOT NULL,
+	CONSTRAINT ttc_onboarding_answers_pk PRIMARY KEY (id)
+);
+
+-- Approved verification requests are kept until the user finished the onboarding
+ALTER TABLE ttc_verification_requests ADD COLUMN IF NOT EXISTS approved_at timestamptz NULL;
+
+COMMIT;
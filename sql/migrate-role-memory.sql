@@ -0,0 +1,25 @@
+-- One-off migration for databases created before the roles of members were restored when they
+-- rejoin.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS role_memory_restore_all bool NOT NULL DEFAULT true;
+
+CREATE TABLE IF NOT EXISTS ttc_role_memory (
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	role_id int8 NOT NULL,
+	left_at timestamptz NOT NULL,
+	CONSTRAINT ttc_role_memory_pkey PRIMARY KEY (guild_id, user_id, role_id)
+);
+
+CREATE TABLE IF NOT EXISTS ttc_role_memory_rules (
+	id serial4 NOT NULL,
+	role_id int8 NOT NULL,
+	restore bool NOT NULL,
+	CONSTRAINT ttc_role_memory_rules_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_role_memory_rules_role_id_key UNIQUE (role_id)
+);
+
+COMMIT;
@@ -0,0 +1,50 @@
+-- One-off migration for databases created before the selfroles were grouped into menus and the
+-- posted menus were kept in sync with the database.
+
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS ttc_selfrole_groups (
+	id serial4 NOT NULL,
+	"name" varchar(256) NOT NULL,
+	description varchar(4000) NULL,
+	exclusive bool NOT NULL DEFAULT false,
+	CONSTRAINT ttc_selfrole_groups_pk PRIMARY KEY (id)
+);
+
+ALTER TABLE ttc_selfroles
+	ADD COLUMN IF NOT EXISTS group_id int4 NULL,
+	ADD COLUMN IF NOT EXISTS required_role_id int8 NULL;
+
+-- Constraints can't be added conditionally, so the constraint is replaced to allow running this twice
+ALTER TABLE ttc_selfroles DROP CONSTRAINT IF EXISTS fk_selfroles_group;
+ALTER TABLE ttc_selfroles ADD CONSTRAINT fk_selfroles_group FOREIGN KEY (group_id) REFERENCES ttc_selfrole_groups(id) ON DELETE SET NULL;
+
+CREATE TABLE IF NOT EXISTS ttc_selfrole_messages (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	channel_id int8 NOT NULL,
+	message_id int8 NOT NULL,
+	group_id int4 NULL,
+	CONSTRAINT ttc_selfrole_messages_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_selfrole_messages_message_id_key UNIQUE (message_id)
+);
+
+-- Notify the bot about changes to the self roles so it can refresh the menus
+CREATE OR REPLACE FUNCTION public.ttc_notify_selfroles_changed()
+ RETURNS trigger
+ LANGUAGE plpgsql
+AS $function$
+BEGIN
+	PERFORM pg_notify('ttc_selfroles_changed', TG_TABLE_NAME);
+	RETURN NULL;
+END;
+$function$;
+
+DROP TRIGGER IF EXISTS ttc_selfroles_changed ON public.ttc_selfroles;
+CREATE TRIGGER ttc_selfroles_changed AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON public.ttc_selfroles
+	FOR EACH STATEMENT EXECUTE FUNCTION ttc_notify_selfroles_changed();
+DROP TRIGGER IF EXISTS ttc_selfrole_groups_changed ON public.ttc_selfrole_groups;
+CREATE TRIGGER ttc_selfrole_groups_changed AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON public.ttc_selfrole_groups
+	FOR EACH STATEMENT EXECUTE FUNCTION ttc_notify_selfroles_changed();
+
+COMMIT;
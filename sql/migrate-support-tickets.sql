@@ -0,0 +1,18 @@
+-- One-off migration for databases created before support tickets stored the incident details,
+-- had an assignee and could be searched by their text.
+
+BEGIN;
+
+ALTER TABLE ttc_support_tickets
+	ADD COLUMN IF NOT EXISTS incident_description text NULL,
+	ADD COLUMN IF NOT EXISTS incident_system_info text NULL,
+	ADD COLUMN IF NOT EXISTS assignee_id int8 NULL;
+
+-- The expression has to match the one used by the ticket search for the index to be used
+CREATE INDEX IF NOT EXISTS ttc_support_tickets_search_idx ON ttc_support_tickets USING gin ((
+	setweight(to_tsvector('english', incident_title), 'A')
+	|| setweight(to_tsvector('english', coalesce(incident_description, '')), 'B')
+	|| setweight(to_tsvector('english', coalesce(incident_system_info, '')), 'C')
+));
+
+COMMIT;
@@ -0,0 +1,16 @@
+-- One-off migration for databases created before roles could be granted temporarily.
+
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS ttc_temp_roles (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	role_id int8 NOT NULL,
+	granted_by int8 NOT NULL,
+	expires_at timestamptz NOT NULL,
+	CONSTRAINT ttc_temp_roles_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_temp_roles_user_role_key UNIQUE (guild_id, user_id, role_id)
+);
+
+COMMIT;
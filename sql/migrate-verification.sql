@@ -0,0 +1,20 @@
+-- One-off migration for databases created before the verification account age was configurable
+-- and too new accounts could ask the moderators for an approval.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS verification_min_account_age int8 NOT NULL DEFAULT 604800,
+	ADD COLUMN IF NOT EXISTS verification_manual_approval bool NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS ttc_verification_requests (
+	user_id int8 NOT NULL,
+	requested_at timestamptz NOT NULL,
+	denied_at timestamptz NULL,
+	CONSTRAINT ttc_verification_requests_pk PRIMARY KEY (user_id)
+);
+
+-- Denied requests are kept, the table may already exist without the column
+ALTER TABLE ttc_verification_requests ADD COLUMN IF NOT EXISTS denied_at timestamptz NULL;
+
+COMMIT;
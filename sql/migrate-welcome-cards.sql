@@ -0,0 +1,8 @@
+-- One-off migration for databases created before welcome messages could have a welcome card.
+
+BEGIN;
+
+ALTER TABLE ttc_config_properties
+	ADD COLUMN IF NOT EXISTS welcome_card_enabled bool NOT NULL DEFAULT false;
+
+COMMIT;
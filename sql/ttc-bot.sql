@@ -23,46 +23,94 @@ CREATE TABLE ttc_config_properties (
 	welcome_channel int8 NOT NULL,
 	verified_role int8 NOT NULL,
 	moderator_role int8 NOT NULL,
+	verification_min_account_age int8 NOT NULL DEFAULT 604800,
+	verification_manual_approval bool NOT NULL DEFAULT false,
+	captcha_enabled bool NOT NULL DEFAULT false,
+	captcha_attempts int4 NOT NULL DEFAULT 3,
+	captcha_timeout int8 NOT NULL DEFAULT 300,
+	verification_request_channel int8 NULL,
+	onboarding_review_channel int8 NULL,
+	welcome_card_enabled bool NOT NULL DEFAULT false,
+	role_memory_restore_all bool NOT NULL DEFAULT true,
+	message_cache_max_age int8 NULL DEFAULT 1209600,
+	message_cache_max_rows int8 NULL,
+	attachment_archive_enabled bool NOT NULL DEFAULT true,
+	attachment_archive_max_size int8 NOT NULL DEFAULT 8388608,
+	attachment_archive_max_age int8 NULL DEFAULT 604800,
+	attachment_archive_budget int8 NULL DEFAULT 1073741824,
+	impersonation_alert_threshold float8 NULL,
+	ghost_ping_window int8 NULL DEFAULT 300,
+	support_helper_role int8 NULL,
+	event_max_age int8 NULL DEFAULT 31536000,
 	CONSTRAINT ttc_config_properties_pk PRIMARY KEY (id)
 );
 
 
--- public.ttc_conveyance_blacklist_channel definition
+-- public.ttc_conveyance_channel definition
 
 -- Drop table
 
--- DROP TABLE ttc_conveyance_blacklist_channel;
+-- DROP TABLE ttc_conveyance_channel;
 
-CREATE TABLE ttc_conveyance_blacklist_channel (
+CREATE TABLE ttc_conveyance_channel (
 	id serial4 NOT NULL,
 	channel_id int8 NOT NULL,
-	CONSTRAINT ttc_conveyance_blacklist_channel_pk PRIMARY KEY (id)
+	CONSTRAINT ttc_conveyance_channel_pk PRIMARY KEY (id)
 );
 
 
--- public.ttc_conveyance_channel definition
+-- public.ttc_log_categories definition
 
 -- Drop table
 
--- DROP TABLE ttc_conveyance_channel;
+-- DROP TABLE ttc_log_categories;
 
-CREATE TABLE ttc_conveyance_channel (
+CREATE TABLE ttc_log_categories (
+	category varchar(32) NOT NULL,
+	enabled bool NOT NULL DEFAULT true,
+	CONSTRAINT ttc_log_categories_pk PRIMARY KEY (category)
+);
+
+INSERT INTO ttc_log_categories (category) VALUES
+	('messages'),
+	('member_join_leave'),
+	('member_updates'),
+	('bans'),
+	('moderation'),
+	('automod'),
+	('voice'),
+	('server_updates');
+
+
+-- public.ttc_log_channels definition
+
+-- Drop table
+
+-- DROP TABLE ttc_log_channels;
+
+CREATE TABLE ttc_log_channels (
 	id serial4 NOT NULL,
+	category varchar(32) NOT NULL,
 	channel_id int8 NOT NULL,
-	CONSTRAINT ttc_conveyance_channel_pk PRIMARY KEY (id)
+	CONSTRAINT ttc_log_channels_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_log_channels_un UNIQUE (category, channel_id),
+	CONSTRAINT fk_log_channels_category FOREIGN KEY (category) REFERENCES ttc_log_categories(category) ON DELETE CASCADE
 );
 
 
--- public.ttc_conveyance_state definition
+-- public.ttc_log_blacklist_channels definition
 
 -- Drop table
 
--- DROP TABLE ttc_conveyance_state;
+-- DROP TABLE ttc_log_blacklist_channels;
 
-CREATE TABLE ttc_conveyance_state (
+CREATE TABLE ttc_log_blacklist_channels (
 	id serial4 NOT NULL,
-	current_message_id int4 NOT NULL,
-	CONSTRAINT ttc_conveyance_state_pk PRIMARY KEY (id)
+	category varchar(32) NOT NULL,
+	channel_id int8 NOT NULL,
+	CONSTRAINT ttc_log_blacklist_channels_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_log_blacklist_channels_un UNIQUE (category, channel_id),
+	CONSTRAINT fk_log_blacklist_channels_category FOREIGN KEY (category) REFERENCES ttc_log_categories(category) ON DELETE CASCADE
 );
 
 
@@ -166,27 +214,228 @@ CREATE TABLE ttc_emoji_download (
 -- DROP TABLE ttc_message_cache;
 
 CREATE TABLE ttc_message_cache (
+	message_id int8 NOT NULL,
+	channel_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	message_time timestamptz NOT NULL,
+	"content" text NOT NULL,
+	attachments text NOT NULL,
+	mentioned_users _int8 NOT NULL DEFAULT '{}',
+	mentioned_roles _int8 NOT NULL DEFAULT '{}',
+	edited_at timestamptz NULL,
+	deleted_at timestamptz NULL,
+	CONSTRAINT ttc_message_cache_pkey PRIMARY KEY (message_id)
+);
+CREATE INDEX ttc_message_cache_message_time_idx ON ttc_message_cache USING btree (message_time);
+CREATE INDEX ttc_message_cache_user_id_idx ON ttc_message_cache USING btree (user_id, message_time);
+
+
+-- public.ttc_attachment_archive definition
+
+-- Drop table
+
+-- DROP TABLE ttc_attachment_archive;
+
+CREATE TABLE ttc_attachment_archive (
 	id serial4 NOT NULL,
-	message_id int8 NULL,
-	channel_id int8 NULL,
-	user_id int8 NULL,
-	message_time timestamptz NULL,
-	"content" varchar(4000) NULL,
-	attachments varchar(2000) NULL,
-	CONSTRAINT ttc_message_cache_pkey PRIMARY KEY (id)
+	message_id int8 NOT NULL,
+	filename varchar(1024) NOT NULL,
+	"path" varchar(4096) NOT NULL,
+	"size" int8 NOT NULL,
+	archived_at timestamptz NOT NULL,
+	CONSTRAINT ttc_attachment_archive_pk PRIMARY KEY (id)
 );
+CREATE INDEX ttc_attachment_archive_message_id_idx ON ttc_attachment_archive USING btree (message_id);
+CREATE INDEX ttc_attachment_archive_archived_at_idx ON ttc_attachment_archive USING btree (archived_at);
 
--- Populating message cache
 
-DO $$
-DECLARE
-   counter INT := 0;
-BEGIN
-    WHILE counter < 500 LOOP
-        counter := counter + 1;
-        INSERT INTO ttc_message_cache DEFAULT VALUES;
-    END LOOP;
-END$$;
+-- public.ttc_events definition
+
+-- Drop table
+
+-- DROP TABLE ttc_events;
+
+CREATE TABLE ttc_events (
+	id bigserial NOT NULL,
+	guild_id int8 NULL,
+	user_id int8 NOT NULL,
+	event_type varchar(32) NOT NULL,
+	event_time timestamptz NOT NULL,
+	payload jsonb NOT NULL,
+	CONSTRAINT ttc_events_pk PRIMARY KEY (id)
+);
+CREATE INDEX ttc_events_user_id_event_time_idx ON ttc_events USING btree (user_id, event_time);
+
+
+-- public.ttc_ghost_ping_exempt_roles definition
+
+-- Drop table
+
+-- DROP TABLE ttc_ghost_ping_exempt_roles;
+
+CREATE TABLE ttc_ghost_ping_exempt_roles (
+	id serial4 NOT NULL,
+	role_id int8 NOT NULL,
+	CONSTRAINT ttc_ghost_ping_exempt_roles_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_ghost_ping_exempt_roles_role_id_key UNIQUE (role_id)
+);
+
+
+-- public.ttc_ghost_ping_disabled_channels definition
+
+-- Drop table
+
+-- DROP TABLE ttc_ghost_ping_disabled_channels;
+
+CREATE TABLE ttc_ghost_ping_disabled_channels (
+	id serial4 NOT NULL,
+	channel_id int8 NOT NULL,
+	CONSTRAINT ttc_ghost_ping_disabled_channels_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_ghost_ping_disabled_channels_channel_id_key UNIQUE (channel_id)
+);
+
+
+-- public.ttc_invite_joins definition
+
+-- Drop table
+
+-- DROP TABLE ttc_invite_joins;
+
+CREATE TABLE ttc_invite_joins (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	inviter_id int8 NULL,
+	invite_code varchar(32) NULL,
+	joined_at timestamptz NOT NULL,
+	left_at timestamptz NULL,
+	CONSTRAINT ttc_invite_joins_pk PRIMARY KEY (id)
+);
+CREATE INDEX ttc_invite_joins_inviter_id_idx ON ttc_invite_joins USING btree (guild_id, inviter_id);
+CREATE INDEX ttc_invite_joins_user_id_idx ON ttc_invite_joins USING btree (guild_id, user_id);
+
+
+-- public.ttc_onboarding_rules definition
+
+-- Drop table
+
+-- DROP TABLE ttc_onboarding_rules;
+
+CREATE TABLE ttc_onboarding_rules (
+	id serial4 NOT NULL,
+	page_order int4 NOT NULL,
+	title varchar(256) NOT NULL,
+	"content" varchar(4000) NOT NULL,
+	CONSTRAINT ttc_onboarding_rules_pk PRIMARY KEY (id)
+);
+
+
+-- public.ttc_onboarding_questions definition
+
+-- Drop table
+
+-- DROP TABLE ttc_onboarding_questions;
+
+CREATE TABLE ttc_onboarding_questions (
+	id serial4 NOT NULL,
+	question_order int4 NOT NULL,
+	question varchar(45) NOT NULL,
+	placeholder varchar(100) NULL,
+	required bool NOT NULL DEFAULT true,
+	CONSTRAINT ttc_onboarding_questions_pk PRIMARY KEY (id)
+);
+
+
+-- public.ttc_onboarding_answers definition
+
+-- Drop table
+
+-- DROP TABLE ttc_onboarding_answers;
+
+CREATE TABLE ttc_onboarding_answers (
+	id serial4 NOT NULL,
+	user_id int8 NOT NULL,
+	question varchar(45) NOT NULL,
+	answer varchar(1024) NOT NULL,
+	answer_time timestamptz NOT NULL,
+	CONSTRAINT ttc_onboarding_answers_pk PRIMARY KEY (id)
+);
+
+-- public.ttc_verification_requests definition
+
+-- Drop table
+
+-- DROP TABLE ttc_verification_requests;
+
+CREATE TABLE ttc_verification_requests (
+	user_id int8 NOT NULL,
+	requested_at timestamptz NOT NULL,
+	approved_at timestamptz NULL,
+	denied_at timestamptz NULL,
+	CONSTRAINT ttc_verification_requests_pk PRIMARY KEY (user_id)
+);
+
+-- public.ttc_role_memory definition
+
+-- Drop table
+
+-- DROP TABLE ttc_role_memory;
+
+CREATE TABLE ttc_role_memory (
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	role_id int8 NOT NULL,
+	left_at timestamptz NOT NULL,
+	CONSTRAINT ttc_role_memory_pkey PRIMARY KEY (guild_id, user_id, role_id)
+);
+
+
+-- public.ttc_role_memory_rules definition
+
+-- Drop table
+
+-- DROP TABLE ttc_role_memory_rules;
+
+CREATE TABLE ttc_role_memory_rules (
+	id serial4 NOT NULL,
+	role_id int8 NOT NULL,
+	restore bool NOT NULL,
+	CONSTRAINT ttc_role_memory_rules_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_role_memory_rules_role_id_key UNIQUE (role_id)
+);
+
+
+-- public.ttc_selfrole_groups definition
+
+-- Drop table
+
+-- DROP TABLE ttc_selfrole_groups;
+
+CREATE TABLE ttc_selfrole_groups (
+	id serial4 NOT NULL,
+	"name" varchar(256) NOT NULL,
+	description varchar(4000) NULL,
+	exclusive bool NOT NULL DEFAULT false,
+	CONSTRAINT ttc_selfrole_groups_pk PRIMARY KEY (id)
+);
+
+
+-- public.ttc_selfrole_messages definition
+
+-- Drop table
+
+-- DROP TABLE ttc_selfrole_messages;
+
+CREATE TABLE ttc_selfrole_messages (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	channel_id int8 NOT NULL,
+	message_id int8 NOT NULL,
+	group_id int4 NULL,
+	CONSTRAINT ttc_selfrole_messages_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_selfrole_messages_message_id_key UNIQUE (message_id)
+);
+
 
 -- public.ttc_selfroles definition
 
@@ -198,6 +447,8 @@ CREATE TABLE ttc_selfroles (
 	id serial4 NOT NULL,
 	role_id int8 NOT NULL,
 	emoji_name varchar NULL,
+	group_id int4 NULL,
+	required_role_id int8 NULL,
 	CONSTRAINT ttc_selfroles_pk PRIMARY KEY (id)
 );
 
@@ -216,9 +467,36 @@ CREATE TABLE ttc_support_tickets (
 	incident_title varchar(128) NOT NULL,
 	incident_solved bool NOT NULL,
 	unarchivals int2 NOT NULL,
+	incident_description text NULL,
+	incident_system_info text NULL,
+	assignee_id int8 NULL,
 	CONSTRAINT ttc_support_tickets_pkey PRIMARY KEY (incident_id),
 	CONSTRAINT ttc_support_tickets_thread_id_key UNIQUE (thread_id)
 );
+-- The expression has to match the one used by the ticket search for the index to be used
+CREATE INDEX ttc_support_tickets_search_idx ON ttc_support_tickets USING gin ((
+	setweight(to_tsvector('english', incident_title), 'A')
+	|| setweight(to_tsvector('english', coalesce(incident_description, '')), 'B')
+	|| setweight(to_tsvector('english', coalesce(incident_system_info, '')), 'C')
+));
+
+
+-- public.ttc_temp_roles definition
+
+-- Drop table
+
+-- DROP TABLE ttc_temp_roles;
+
+CREATE TABLE ttc_temp_roles (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	role_id int8 NOT NULL,
+	granted_by int8 NOT NULL,
+	expires_at timestamptz NOT NULL,
+	CONSTRAINT ttc_temp_roles_pk PRIMARY KEY (id),
+	CONSTRAINT ttc_temp_roles_user_role_key UNIQUE (guild_id, user_id, role_id)
+);
 
 
 -- public.ttc_webhooks definition
@@ -257,7 +535,6 @@ CREATE TABLE ttc_config (
 	id serial4 NOT NULL,
 	config_properties_id serial4 NOT NULL,
 	conveyance_id serial4,
-	conveyance_blacklist_id serial4,
 	welcome_message_id serial4,
 	harold_emoji_id serial4,
 	CONSTRAINT ttc_config_pk PRIMARY KEY (id, config_properties_id)
@@ -281,14 +558,16 @@ CREATE TABLE ttc_embed_colors (
 -- public.ttc_config constraint definition
 
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_conveyance FOREIGN KEY (conveyance_id) REFERENCES ttc_conveyance_channel(id);
-ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_conveyance_blacklist FOREIGN KEY (conveyance_blacklist_id) REFERENCES ttc_conveyance_blacklist_channel(id);
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_properties FOREIGN KEY (config_properties_id) REFERENCES ttc_config_properties(id);
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_welcome FOREIGN KEY (welcome_message_id) REFERENCES ttc_welcome_message(id);
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_harold_emoji FOREIGN KEY (harold_emoji_id) REFERENCES ttc_harold_emoji(id);
 
+-- public.ttc_selfroles constraint definition
+
+ALTER TABLE public.ttc_selfroles ADD CONSTRAINT fk_selfroles_group FOREIGN KEY (group_id) REFERENCES ttc_selfrole_groups(id) ON DELETE SET NULL;
+
 ALTER TABLE public.ttc_config ALTER COLUMN welcome_message_id DROP NOT NULL;
 ALTER TABLE public.ttc_config ALTER COLUMN harold_emoji_id DROP NOT NULL;
-ALTER TABLE public.ttc_config ALTER COLUMN conveyance_blacklist_id DROP NOT NULL;
 ALTER TABLE public.ttc_config ALTER COLUMN conveyance_id DROP NOT NULL;
 
 
@@ -301,13 +580,48 @@ AS SELECT tc.id AS config_id,
     tcp.welcome_channel AS welcome_channel,
     tcp.verified_role AS verified_role,
     tcp.moderator_role AS moderator_role,
-    tcbc.channel_id AS conveyance_blacklist_channel,
+    tcp.verification_min_account_age AS verification_min_account_age,
+    tcp.verification_manual_approval AS verification_manual_approval,
+    tcp.captcha_enabled AS captcha_enabled,
+    tcp.captcha_attempts AS captcha_attempts,
+    tcp.captcha_timeout AS captcha_timeout,
+    tcp.verification_request_channel AS verification_request_channel,
+    tcp.onboarding_review_channel AS onboarding_review_channel,
+    tcp.welcome_card_enabled AS welcome_card_enabled,
+    tcp.role_memory_restore_all AS role_memory_restore_all,
+    tcp.message_cache_max_age AS message_cache_max_age,
+    tcp.message_cache_max_rows AS message_cache_max_rows,
+    tcp.attachment_archive_enabled AS attachment_archive_enabled,
+    tcp.attachment_archive_max_size AS attachment_archive_max_size,
+    tcp.attachment_archive_max_age AS attachment_archive_max_age,
+    tcp.attachment_archive_budget AS attachment_archive_budget,
+    tcp.impersonation_alert_threshold AS impersonation_alert_threshold,
+    tcp.ghost_ping_window AS ghost_ping_window,
+    tcp.support_helper_role AS support_helper_role,
+    tcp.event_max_age AS event_max_age,
     tcc.channel_id AS conveyance_channel,
     the.name AS harold_emoji,
     twm.welcome_message AS welcome_message
    FROM ttc_config tc
      FULL JOIN ttc_config_properties tcp ON tc.config_properties_id = tcp.id
-     FULL JOIN ttc_conveyance_blacklist_channel tcbc ON tc.conveyance_blacklist_id = tcbc.id
      FULL JOIN ttc_conveyance_channel tcc ON tc.conveyance_id = tcc.id
      FULL JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
      FULL JOIN ttc_welcome_message twm ON tc.welcome_message_id = twm.id;
+
+
+-- Notify the bot about changes to the self roles so it can refresh the menus
+
+CREATE OR REPLACE FUNCTION public.ttc_notify_selfroles_changed()
+ RETURNS trigger
+ LANGUAGE plpgsql
+AS $function$
+BEGIN
+	PERFORM pg_notify('ttc_selfroles_changed', TG_TABLE_NAME);
+	RETURN NULL;
+END;
+$function$;
+
+CREATE TRIGGER ttc_selfroles_changed AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON public.ttc_selfroles
+	FOR EACH STATEMENT EXECUTE FUNCTION ttc_notify_selfroles_changed();
+CREATE TRIGGER ttc_selfrole_groups_changed AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON public.ttc_selfrole_groups
+	FOR EACH STATEMENT EXECUTE FUNCTION ttc_notify_selfroles_changed();
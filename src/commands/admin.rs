@@ -4,11 +4,13 @@
 
 use std::time::Instant;
 
-use poise::serenity_prelude::{ButtonStyle, CreateSelectMenu, Emoji, GuildChannel, Role, RoleId};
-use std::collections::HashMap;
+use poise::serenity_prelude::{ButtonStyle, ChannelId, GuildChannel};
 
 use crate::{
-    traits::context_ext::ContextExt, traits::readable::Readable, utils::emoji_cache::EmojiCache,
+    commands::support,
+    traits::context_ext::ContextExt,
+    traits::readable::Readable,
+    utils::{emoji_cache::EmojiCache, selfroles},
     Context, Error,
 };
 
@@ -98,10 +100,12 @@ pub async fn create_verification(
     Ok(())
 }
 
-/// Create selfroles message
+/// Create selfroles messages
 ///
-/// Command to create message for managing permissions
-/// ``create_verification [channel_id] [roles (seperated by commas)]``
+/// Command to create the messages for managing self roles, one for every group
+/// ``create_selfroles [channel_id] [group_id]``
+///
+/// Roles without a group are put into a single menu, ``group_id`` only creates the menu of that group
 #[poise::command(
     prefix_command,
     slash_command,
@@ -113,74 +117,136 @@ pub async fn create_verification(
 pub async fn create_selfroles(
     ctx: Context<'_>,
     #[description = "Channel to send it in"] channel: GuildChannel,
+    #[description = "Only create the menu of this group"] group: Option<i32>,
 ) -> Result<(), Error> {
     // Get the channel and guild ids
     let guild_id = ctx.guild_id().unwrap();
 
-    // Create the selection menu
-    let mut menu = CreateSelectMenu::default();
-    menu.custom_id("ttc-bot-self-role-menu");
+    if let Some(group) = group {
+        if !ctx
+            .data()
+            .config
+            .selfrole_groups()
+            .await?
+            .iter()
+            .any(|g| g.0 == group)
+        {
+            return Err(Error::from(format!(
+                "Invalid selfrole group with ID {}",
+                group
+            )));
+        }
+    }
 
-    let raw_selfroles = ctx.data().config.selfroles().await?;
+    let built = selfroles::build_selfrole_menus(
+        ctx.serenity_context(),
+        guild_id,
+        &ctx.data().config,
+        &ctx.data().colors,
+        group,
+    )
+    .await?;
+    let skipped_groups = built
+        .skipped_groups
+        .into_iter()
+        .map(|(_, problem)| problem)
+        .collect::<Vec<String>>();
 
-    if raw_selfroles.is_empty() {
-        return Err(Error::from("No roles in the Database"));
+    if built.menus.is_empty() {
+        return Err(Error::from(match (group, skipped_groups.first()) {
+            (_, Some(problem)) => problem.clone(),
+            (Some(group), None) => format!("The selfrole group with ID {} has no roles", group),
+            (None, None) => "No roles in the Database".to_string(),
+        }));
     }
 
-    // Set the menu values properly
-    menu.min_values(0);
-    menu.max_values(raw_selfroles.len() as u64);
-
-    let role_hmap = guild_id.roles(ctx).await?;
-    let emojis = guild_id.emojis(ctx).await?;
+    // Create the menus in the specified channel and remember them for refreshing later
+    let menu_count = built.menus.len();
+    for menu in built.menus {
+        let message = channel
+            .send_message(ctx, |m| {
+                m.components(|c| c.create_action_row(|a| a.add_select_menu(menu.menu)))
+                    .set_embed(menu.embed)
+            })
+            .await?;
 
-    let mut option_data: Vec<(Role, Option<&Emoji>)> = Vec::new();
-    let mut emoji_hmap = HashMap::new();
-    for emoji in &emojis {
-        emoji_hmap.insert(emoji.name.clone(), emoji.clone());
+        sqlx::query!(
+            r#"INSERT INTO ttc_selfrole_messages (guild_id, channel_id, message_id, group_id) VALUES($1, $2, $3, $4)"#,
+            guild_id.0 as i64,
+            channel.id.0 as i64,
+            message.id.0 as i64,
+            menu.group_id,
+        )
+        .execute(&*ctx.data().pool)
+        .await?;
     }
 
-    for val in raw_selfroles {
-        let role = match role_hmap.get(&RoleId(val.0 as u64)) {
-            Some(role) => role,
-            None => {
-                return Err(Error::from(format!("Invalid role with ID {}", val.0)));
-            }
-        };
-        let emoji = emoji_hmap.get(&val.1.unwrap_or(String::from("")));
-        option_data.push((role.clone(), emoji));
-    }
+    // Reply to the user
+    ctx.send_simple(
+        false,
+        "Self-role menus created",
+        Some(&format!(
+            "{} self-role menu(s) created in <#{}>.{}",
+            menu_count,
+            channel.id,
+            problems_notice(&built.stale_roles, &skipped_groups)
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
 
-    // Create the options for the roles
-    menu.options(|m| {
-        for val in option_data {
-            let role = val.0;
-            match val.1 {
-                Some(emoji) => {
-                    m.create_option(|o| o.label(role.name).value(role.id).emoji(emoji.clone()));
-                }
-                None => {
-                    m.create_option(|o| o.label(role.name).value(role.id));
-                }
-            }
-        }
-        m
-    });
+    Ok(())
+}
 
-    // Create the menu in the specified channel
-    let color = ctx.data().colors.selfrole_selection().await;
-    channel
-        .send_message(ctx, |m| {
-            m.components(|c| c.create_action_row(|a| a.add_select_menu(menu)))
-                .embed(|e| e.title("Manage your self roles here").color(color))
-        })
-        .await?;
+/// Manage the self role menus
+///
+/// Commands to manage the posted self role menus
+/// ``selfroles refresh``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    owners_only,
+    hide_in_help,
+    category = "Admin",
+    subcommands("selfroles_refresh")
+)]
+pub async fn selfroles(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Refresh the self role menus
+///
+/// Update every posted self role menu to match the database
+/// ``selfroles refresh``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    owners_only,
+    hide_in_help,
+    category = "Admin",
+    rename = "refresh"
+)]
+pub async fn selfroles_refresh(ctx: Context<'_>) -> Result<(), Error> {
+    let report = selfroles::refresh_selfrole_menus(
+        ctx.serenity_context(),
+        ctx.guild_id().unwrap(),
+        &ctx.data().pool,
+        &ctx.data().config,
+        &ctx.data().colors,
+    )
+    .await?;
 
-    // Reply to the user
     ctx.send_simple(
         false,
-        "Self-role menu created",
-        Some(&format!("Self-role menu created in <#{}>.", channel.id)),
+        "Self-role menus refreshed",
+        Some(&format!(
+            "{} self-role menu(s) updated, {} deleted menu(s) forgotten.{}",
+            report.updated,
+            report.removed,
+            problems_notice(&report.stale_roles, &report.skipped_groups)
+        )),
         ctx.data().colors.admin_success().await,
     )
     .await?;
@@ -188,6 +254,29 @@ pub async fn create_selfroles(
     Ok(())
 }
 
+/// List the roles that are still configured as self roles but don't exist anymore and the groups
+/// that couldn't be shown
+fn problems_notice(stale_roles: &[i64], skipped_groups: &[String]) -> String {
+    let mut notice = String::new();
+    if !stale_roles.is_empty() {
+        notice.push_str(&format!(
+            "\n\nThese roles don't exist anymore and were left out: {}",
+            stale_roles
+                .iter()
+                .map(|role| role.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+    if !skipped_groups.is_empty() {
+        notice.push_str(&format!(
+            "\n\nThese groups were left out:\n{}",
+            skipped_groups.join("\n")
+        ));
+    }
+    notice
+}
+
 /// Create support ticket button
 ///
 /// Command to create the button for support tickets
@@ -288,3 +377,89 @@ pub async fn rebuild_emoji_cache(ctx: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Backfill support ticket details
+///
+/// Fill in the description and system info of old support tickets from the starter messages of \
+/// their threads
+/// ``backfill_tickets``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn backfill_tickets(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &*ctx.data().pool;
+    // Anonymised tickets had their details erased on request, those must not come back
+    let tickets = sqlx::query!(
+        r#"SELECT incident_id, thread_id FROM ttc_support_tickets WHERE incident_description IS NULL AND user_id <> 0"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    ctx.send_simple(
+        false,
+        "Starting to backfill the support tickets",
+        Some(&format!(
+            "{} tickets are missing their details",
+            tickets.len()
+        )),
+        ctx.data().colors.support_info().await,
+    )
+    .await?;
+
+    let mut filled = 0;
+    let mut unavailable = 0;
+    for ticket in tickets {
+        let starter = support::ticket_summary_message(
+            ctx.serenity_context(),
+            ChannelId(ticket.thread_id as u64),
+        )
+        .await
+        .ok();
+        let embed = match starter.and_then(|starter| starter.embeds.into_iter().next()) {
+            Some(embed) => embed,
+            None => {
+                log::warn!(
+                    "Unable to find the starter message of support ticket {}",
+                    ticket.incident_id
+                );
+                unavailable += 1;
+                continue;
+            }
+        };
+
+        let field = |name: &str| {
+            embed
+                .fields
+                .iter()
+                .find(|field| field.name == name)
+                .map(|field| field.value.clone())
+        };
+        sqlx::query!(
+            r#"UPDATE ttc_support_tickets SET incident_description = $1, incident_system_info = $2 WHERE incident_id = $3"#,
+            field("Description").unwrap_or_default(),
+            field("System info").unwrap_or_default(),
+            ticket.incident_id
+        )
+        .execute(pool)
+        .await?;
+        filled += 1;
+    }
+
+    ctx.send_simple(
+        false,
+        "Finished backfilling the support tickets",
+        Some(&format!(
+            "Filled in {} tickets, {} tickets no longer have a starter message",
+            filled, unavailable
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,13 +1,19 @@
 use crate::{
     traits::context_ext::ContextExt, traits::readable::Readable, types::data::Data,
-    utils::emoji_cache::EmojiCache, utils::userinfo, utils::userinfo::userinfo_fn, Context, Error,
+    utils::emoji_cache::EmojiCache, utils::user_data, utils::userinfo,
+    utils::userinfo::userinfo_fn, Context, Error,
 };
 use futures::StreamExt;
 use poise::{
-    serenity_prelude::{CreateEmbed, Member, User},
+    serenity_prelude::{
+        AttachmentType, ButtonStyle, CreateEmbed, InteractionResponseType, Member, User,
+    },
     Command,
 };
-use std::{collections::HashMap, iter::Iterator, time::Duration};
+use std::{borrow::Cow, collections::HashMap, iter::Iterator, time::Duration};
+
+// How long the deletion of user data waits for a confirmation
+const MYDATA_CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
 // ----------------------
 // General group commands
 // ----------------------
@@ -644,3 +650,225 @@ pub async fn version(ctx: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Invite statistics of a user
+///
+/// Shows how many members joined with the invites of a user and how many of them stayed
+/// ``invites [user (optional, defaults to self)]``
+#[poise::command(prefix_command, slash_command, guild_only, category = "General")]
+pub async fn invites(
+    ctx: Context<'_>,
+    #[description = "The user to view the invites of, defaults to self"] user: Option<User>,
+) -> Result<(), Error> {
+    let user = user.unwrap_or(ctx.author().clone());
+    let guild_id = ctx.guild_id().unwrap().0 as i64;
+    let pool = &*ctx.data().pool;
+
+    // Members who joined several times are only counted once
+    let totals = sqlx::query!(
+        r#"SELECT COUNT(DISTINCT user_id) AS "invited!",
+        COUNT(DISTINCT user_id) FILTER (WHERE left_at IS NULL) AS "stayed!"
+        FROM ttc_invite_joins WHERE guild_id = $1 AND inviter_id = $2"#,
+        guild_id,
+        user.id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?;
+    let codes = sqlx::query!(
+        r#"SELECT invite_code AS "invite_code!", COUNT(*) AS "uses!" FROM ttc_invite_joins
+        WHERE guild_id = $1 AND inviter_id = $2 AND invite_code IS NOT NULL
+        GROUP BY invite_code ORDER BY "uses!" DESC LIMIT 10"#,
+        guild_id,
+        user.id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let color = ctx.data().colors.invite_stats().await;
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Invites of {}", user.tag()))
+                .color(color)
+                .field("Members invited", totals.invited, true)
+                .field("Still here", totals.stayed, true)
+                .field("Left", totals.invited - totals.stayed, true);
+            if !codes.is_empty() {
+                e.field(
+                    "Most used invites",
+                    codes
+                        .iter()
+                        .map(|code| format!("`{}` - {} joins", code.invite_code, code.uses))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                    false,
+                );
+            }
+            e
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the data stored about you
+///
+/// Commands to get a copy of the data the bot stores about you or to have it deleted
+/// ``mydata [export|delete]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "General",
+    subcommands("mydata_export", "mydata_delete")
+)]
+pub async fn mydata(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Get the data stored about you
+///
+/// Sends you a JSON file with everything the bot stores about you in your DMs
+/// ``mydata export``
+#[poise::command(prefix_command, slash_command, category = "General", rename = "export")]
+pub async fn mydata_export(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let data = user_data::export_user_data(&ctx.data().pool, ctx.author().id).await?;
+    let file = serde_json::to_vec_pretty(&data)?;
+
+    let dm_result = ctx
+        .author()
+        .direct_message(ctx, |m| {
+            m.content("Here is the data stored about you.")
+                .add_file(AttachmentType::Bytes {
+                    data: Cow::from(file),
+                    filename: format!("ttc-bot-data-{}.json", ctx.author().id),
+                })
+        })
+        .await;
+
+    match dm_result {
+        Ok(_) => {
+            ctx.send_simple(
+                true,
+                "Data sent",
+                Some("The data stored about you has been sent to your DMs."),
+                ctx.data().colors.user_data().await,
+            )
+            .await?;
+        }
+        Err(why) => {
+            log::warn!("Unable to send the data of a user to them: {}", why);
+            ctx.send_simple(
+                true,
+                "Unable to send DM",
+                Some("Allow direct messages from server members and try again."),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete the data stored about you
+///
+/// Removes your cached messages and their archived attachments, emoji statistics, onboarding answers and invite history and \
+/// anonymises your support tickets after a confirmation. Moderation records are kept without the \
+/// text of your messages.
+/// ``mydata delete``
+#[poise::command(prefix_command, slash_command, category = "General", rename = "delete")]
+pub async fn mydata_delete(ctx: Context<'_>) -> Result<(), Error> {
+    let color = ctx.data().colors.user_data().await;
+    let reply = ctx
+        .send(|m| {
+            m.embed(|e| {
+                e.title("Delete your data?")
+                    .description(
+                        "This removes your cached messages and their archived attachments, \
+                        emoji statistics, onboarding answers and invite history, and anonymises your support tickets. Moderation \
+                        records like deleted and edited messages are kept, but the text and \
+                        attachments of your messages are removed from them. This can't be undone.",
+                    )
+                    .color(color)
+            })
+            .components(|c| {
+                c.create_action_row(|a| {
+                    a.create_button(|b| {
+                        b.label("Delete")
+                            .style(ButtonStyle::Danger)
+                            .custom_id("ttc-mydata-delete")
+                    })
+                    .create_button(|b| {
+                        b.label("Cancel")
+                            .style(ButtonStyle::Secondary)
+                            .custom_id("ttc-mydata-cancel")
+                    })
+                })
+            })
+            .ephemeral(true)
+        })
+        .await?;
+    let message = reply.message().await?.into_owned();
+
+    let interaction = match message
+        .await_component_interaction(ctx)
+        .timeout(MYDATA_CONFIRM_TIMEOUT)
+        .author_id(ctx.author().id)
+        .await
+    {
+        Some(interaction) => interaction,
+        None => {
+            reply
+                .edit(ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Deletion cancelled")
+                            .description("No confirmation was given in time.")
+                            .color(color)
+                    })
+                    .components(|c| c)
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let embed = match interaction.data.custom_id.as_str() {
+        "ttc-mydata-delete" => {
+            let deleted = user_data::delete_user_data(&ctx.data().pool, ctx.author().id).await?;
+            log::info!(
+                "Deleted the data of user {} on their request, {} rows affected",
+                ctx.author().id,
+                deleted.total()
+            );
+            let mut embed = CreateEmbed::default();
+            embed
+                .title("Data deleted")
+                .color(color)
+                .field("Cached messages", deleted.messages, true)
+                .field("Archived attachments", deleted.archived_attachments, true)
+                .field("Emoji statistics", deleted.emoji_stats, true)
+                .field("Onboarding answers", deleted.onboarding_answers, true)
+                .field("Invite history", deleted.invite_joins, true)
+                .field("Anonymised tickets", deleted.support_tickets, true)
+                .field("Cleared moderation records", deleted.event_contents, true);
+            embed
+        }
+        _ => {
+            let mut embed = CreateEmbed::default();
+            embed
+                .title("Deletion cancelled")
+                .description("Your data has not been changed.")
+                .color(color);
+            embed
+        }
+    };
+    interaction
+        .create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+        })
+        .await?;
+
+    Ok(())
+}
@@ -1,13 +1,41 @@
 use crate::{
     traits::{context_ext::ContextExt, readable::Readable},
+    types::{event_type::EventType, log_category::LogCategory, message_state::MessageState},
     utils::{
         bee_utils::{BeeifiedUser, BeezoneChannel},
-        helper_functions::is_user_timed_out,
+        event_store,
+        helper_functions::{is_user_timed_out, shorten},
+        message_cache::{self, MessageFilter},
+        pagination,
+        temp_roles::log_temp_role,
     },
     Context, Error,
 };
-use chrono::{Duration, Utc};
-use poise::serenity_prelude::{Member, Timestamp, UserId};
+use chrono::{DateTime, Duration, Utc};
+use poise::serenity_prelude::{
+    AttachmentType, Channel, Color, CreateEmbed, Guild, GuildChannel, GuildId, Member, Role,
+    Timestamp, User, UserId,
+};
+use sqlx::PgPool;
+use std::{borrow::Cow, future};
+
+// The most temporary roles listed at once
+const TEMP_ROLE_LIST_LIMIT: usize = 30;
+// How many events are shown on a page of the history
+const HISTORY_PAGE_SIZE: usize = 10;
+// How many of the newest events can be paged through in the history
+const HISTORY_EVENT_LIMIT: i64 = 1000;
+// How many characters the fields of a history page can take up, leaving room for the title and
+// footer within the 6000 characters of an embed
+const HISTORY_PAGE_LENGTH: usize = 5700;
+// How many messages are shown on a page of the message log
+const MESSAGE_LOG_PAGE_SIZE: i64 = 10;
+// The most characters of a message shown in the message log
+const MESSAGE_LOG_CONTENT_LENGTH: usize = 200;
+// The most bytes written into an export of the message log, staying below the upload limit
+const MESSAGE_LOG_EXPORT_SIZE: usize = 8 * 1024 * 1024;
+// How many messages are read at once while writing an export
+const MESSAGE_LOG_EXPORT_BATCH: i64 = 1000;
 
 /// Ban a member
 ///
@@ -564,3 +592,615 @@ pub async fn unbeezone(ctx: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Manage temporary roles
+///
+/// Commands to give members roles for a limited time
+/// ``temprole [add|list]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_ROLES",
+    guild_only,
+    subcommands("temprole_add", "temprole_list")
+)]
+pub async fn temprole(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Give a member a temporary role
+///
+/// Command to give a member a role that is taken away again after the duration
+/// ``temprole add [member] [role] [duration]``
+///
+/// ``duration`` is a human-readable string like \
+/// ``1h``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_ROLES",
+    guild_only,
+    rename = "add"
+)]
+pub async fn temprole_add(
+    ctx: Context<'_>,
+    #[description = "The member to give the role"] mut member: Member,
+    #[description = "The role to give"] role: Role,
+    #[description = "The time to give the role for"]
+    #[rename = "duration"]
+    duration_str: String,
+) -> Result<(), Error> {
+    let duration = humantime::parse_duration(&duration_str)?;
+    // ~110 years; it's mainly here to prevent the bot from panicking
+    if duration.as_secs() > 3456000000 {
+        return Err(Error::from("Provided time is too long."));
+    }
+
+    // Moderators shouldn't be able to hand out roles above their own
+    let author = ctx.author_member().await.unwrap();
+    let is_owner = ctx
+        .guild()
+        .map_or(false, |guild| guild.owner_id == author.user.id);
+    if let Some((_, position)) = author.highest_role_info(ctx.serenity_context()) {
+        if role.position >= position && !is_owner {
+            ctx.send_simple(
+                true,
+                "Role too high",
+                Some("You can only give out roles below your highest role."),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    // A role the member already has for good would be taken away on expiry, only temporary ones
+    // can be extended
+    if member.roles.contains(&role.id)
+        && sqlx::query!(
+            r#"SELECT id FROM ttc_temp_roles WHERE guild_id = $1 AND user_id = $2 AND role_id = $3"#,
+            member.guild_id.0 as i64,
+            member.user.id.0 as i64,
+            role.id.0 as i64,
+        )
+        .fetch_optional(&*ctx.data().pool)
+        .await?
+        .is_none()
+    {
+        ctx.send_simple(
+            true,
+            "Role already held",
+            Some(&format!(
+                "{} already has <@&{}> permanently.",
+                member.user.tag(),
+                role.id
+            )),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let expires_at = Utc::now() + Duration::from_std(duration)?;
+
+    // The grant is stored before the role is given, so the expiry can't miss a role that was
+    // given. Granting the role again only moves the expiry.
+    let grant = sqlx::query!(
+        r#"INSERT INTO ttc_temp_roles (guild_id, user_id, role_id, granted_by, expires_at) VALUES($1, $2, $3, $4, $5)
+        ON CONFLICT (guild_id, user_id, role_id) DO UPDATE SET granted_by = EXCLUDED.granted_by, expires_at = EXCLUDED.expires_at
+        RETURNING id, (xmax = 0) AS "inserted!""#,
+        member.guild_id.0 as i64,
+        member.user.id.0 as i64,
+        role.id.0 as i64,
+        ctx.author().id.0 as i64,
+        expires_at,
+    )
+    .fetch_one(&*ctx.data().pool)
+    .await?;
+
+    if let Err(why) = member.add_role(ctx, role.id).await {
+        // An extended grant still belongs to the role the member already has
+        if grant.inserted {
+            sqlx::query!(r#"DELETE FROM ttc_temp_roles WHERE id = $1"#, grant.id)
+                .execute(&*ctx.data().pool)
+                .await?;
+        }
+        return Err(why.into());
+    }
+
+    log_temp_role(
+        ctx.serenity_context(),
+        &ctx.data().config,
+        "Temporary role granted",
+        &format!(
+            "Granted by <@{}> until <t:{}:F>.",
+            ctx.author().id,
+            expires_at.timestamp()
+        ),
+        member.user.id,
+        role.id,
+        ctx.data().colors.conveyance_temp_role_added().await,
+    )
+    .await?;
+
+    ctx.send_simple(
+        false,
+        "Temporary role granted",
+        Some(&format!(
+            "{} has been given <@&{}> for {}, it will be removed <t:{}:R>.",
+            member.user.tag(),
+            role.id,
+            duration.readable(),
+            expires_at.timestamp()
+        )),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List the temporary roles
+///
+/// Command to list the temporary roles that haven't expired yet
+/// ``temprole list [member (optional)]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_ROLES",
+    guild_only,
+    rename = "list"
+)]
+pub async fn temprole_list(
+    ctx: Context<'_>,
+    #[description = "Only list the roles of this member"] member: Option<Member>,
+) -> Result<(), Error> {
+    let temp_roles = sqlx::query!(
+        r#"SELECT * FROM ttc_temp_roles WHERE guild_id = $1 AND ($2::int8 IS NULL OR user_id = $2) ORDER BY expires_at ASC"#,
+        ctx.guild_id().unwrap().0 as i64,
+        member.map(|member| member.user.id.0 as i64),
+    )
+    .fetch_all(&*ctx.data().pool)
+    .await?;
+
+    if temp_roles.is_empty() {
+        ctx.send_simple(
+            true,
+            "No temporary roles",
+            Some("There are no temporary roles right now."),
+            ctx.data().colors.mod_success().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // Keep the list short enough to fit into the embed
+    let mut lines: Vec<String> = temp_roles
+        .iter()
+        .take(TEMP_ROLE_LIST_LIMIT)
+        .map(|temp_role| {
+            format!(
+                "<@{}>: <@&{}>, expires <t:{}:R>",
+                temp_role.user_id,
+                temp_role.role_id,
+                temp_role.expires_at.timestamp()
+            )
+        })
+        .collect();
+    if temp_roles.len() > TEMP_ROLE_LIST_LIMIT {
+        lines.push(format!(
+            "...and {} more",
+            temp_roles.len() - TEMP_ROLE_LIST_LIMIT
+        ));
+    }
+
+    ctx.send_simple(
+        true,
+        "Temporary roles",
+        Some(&lines.join("\n")),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the remembered roles
+///
+/// Commands to manage the roles remembered for members who left
+/// ``rolememory [clear]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_ROLES",
+    guild_only,
+    subcommands("rolememory_clear")
+)]
+pub async fn rolememory(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Forget the roles of a user
+///
+/// Command to forget the roles a user had when they left, so they aren't given back on rejoin
+/// ``rolememory clear [user_id]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_ROLES",
+    guild_only,
+    rename = "clear"
+)]
+pub async fn rolememory_clear(
+    ctx: Context<'_>,
+    #[description = "The user to forget the roles of"] user: UserId,
+) -> Result<(), Error> {
+    let result = sqlx::query!(
+        r#"DELETE FROM ttc_role_memory WHERE guild_id = $1 AND user_id = $2"#,
+        ctx.guild_id().unwrap().0 as i64,
+        user.0 as i64
+    )
+    .execute(&*ctx.data().pool)
+    .await?;
+
+    ctx.send_simple(
+        false,
+        "Role memory cleared",
+        Some(&format!(
+            "Forgot {} role(s) of <@{}>.",
+            result.rows_affected(),
+            user
+        )),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// View the stored events of a user
+///
+/// Command to page through the deletes, edits, joins, leaves, bans and member updates stored for \
+/// a user, newest first
+/// ``history [user] [type (optional)] [since (optional)]``
+///
+/// ``since`` is a human-readable string like \
+/// ``7d``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MODERATE_MEMBERS",
+    guild_only
+)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "The user to view the history of"] user: User,
+    #[description = "Only show events of this type"]
+    #[rename = "type"]
+    event_type: Option<EventType>,
+    #[description = "Only show events from this long ago onwards, for example 7d"]
+    #[rename = "since"]
+    since_str: Option<String>,
+) -> Result<(), Error> {
+    let since = match since_str {
+        Some(since_str) => {
+            let duration = humantime::parse_duration(&since_str)?;
+            Some(Utc::now() - Duration::from_std(duration)?)
+        }
+        None => None,
+    };
+
+    let pool = &*ctx.data().pool;
+    let guild_id = ctx.guild_id().unwrap();
+    let count = event_store::count_events(pool, guild_id, user.id, event_type, since).await?;
+    if count == 0 {
+        ctx.send_simple(
+            true,
+            "No events found",
+            Some(&format!("No stored events match <@{}>.", user.id)),
+            ctx.data().colors.input_warn().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // The events are split into pages by size, so they're all read up front
+    let events = event_store::fetch_events(
+        pool,
+        guild_id,
+        user.id,
+        event_type,
+        since,
+        0,
+        HISTORY_EVENT_LIMIT,
+    )
+    .await?;
+    let fields = events
+        .iter()
+        .map(|event| {
+            let name = EventType::from_db(&event.event_type)
+                .map(|event_type| event_type.label())
+                .unwrap_or("Unknown event");
+            let value = format!(
+                "<t:{}:f>\n{}",
+                event.event_time.timestamp(),
+                event_store::describe_event(&event.event_type, &event.payload)
+            );
+            (name.to_string(), shorten(&value, 1021))
+        })
+        .collect::<Vec<(String, String)>>();
+    let pages = event_store::split_pages(fields, HISTORY_PAGE_SIZE, HISTORY_PAGE_LENGTH);
+    let notice = if count > HISTORY_EVENT_LIMIT {
+        format!(
+            ", showing the newest {} of {} events",
+            HISTORY_EVENT_LIMIT, count
+        )
+    } else {
+        String::new()
+    };
+
+    let color = ctx.data().colors.mod_history().await;
+    pagination::paginate(ctx, pages.len(), true, |page| {
+        future::ready(Ok(history_page(&user, &pages, page, &notice, color)))
+    })
+    .await
+}
+
+/// Build the embed for a page of the history of a user
+fn history_page(
+    user: &User,
+    pages: &[Vec<(String, String)>],
+    page: usize,
+    notice: &str,
+    color: Color,
+) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("History of {}", user.tag()))
+        .color(color)
+        .footer(|f| f.text(format!("Page {}/{}{}", page + 1, pages.len(), notice)))
+        .fields(pages[page].iter().map(|(name, value)| (name, value, false)));
+    embed
+}
+
+/// Search the message cache
+///
+/// Command to search the cached messages by author, channel, content and time, newest first
+/// ``messagelog [user (optional)] [channel (optional)] [content (optional)] [regex (optional)] [since (optional)] [until (optional)] [only (optional)] [export (optional)]``
+///
+/// ``since`` and ``until`` are human-readable strings of how long ago, like \
+/// ``7d``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MODERATE_MEMBERS",
+    guild_only
+)]
+pub async fn messagelog(
+    ctx: Context<'_>,
+    #[description = "Only show messages sent by this user"] user: Option<User>,
+    #[description = "Only show messages sent in this channel"] channel: Option<GuildChannel>,
+    #[description = "Only show messages containing this text"] content: Option<String>,
+    #[description = "Only show messages matching this regular expression"]
+    #[rename = "regex"]
+    pattern: Option<String>,
+    #[description = "Only show messages from this long ago onwards, for example 7d"]
+    #[rename = "since"]
+    since_str: Option<String>,
+    #[description = "Only show messages from up to this long ago, for example 1h"]
+    #[rename = "until"]
+    until_str: Option<String>,
+    #[description = "Only show deleted or edited messages"]
+    #[rename = "only"]
+    state: Option<MessageState>,
+    #[description = "Send the matching messages as a text file"] export: Option<bool>,
+) -> Result<(), Error> {
+    let ago = |duration_str: Option<String>| -> Result<Option<DateTime<Utc>>, Error> {
+        match duration_str {
+            Some(duration_str) => {
+                let duration = humantime::parse_duration(&duration_str)?;
+                Ok(Some(Utc::now() - Duration::from_std(duration)?))
+            }
+            None => Ok(None),
+        }
+    };
+    let filter = MessageFilter {
+        user_id: user.map(|user| user.id.0 as i64),
+        channel_id: channel.map(|channel| channel.id.0 as i64),
+        content,
+        pattern,
+        since: ago(since_str)?,
+        until: ago(until_str)?,
+        state,
+        channel_ids: readable_channels(
+            &ctx.guild().unwrap(),
+            &ctx.author_member().await.unwrap(),
+            &ctx.data()
+                .config
+                .log_blacklist_channels(LogCategory::Messages)
+                .await?,
+        ),
+    };
+
+    let pool = &*ctx.data().pool;
+    if let Some(pattern) = &filter.pattern {
+        if !message_cache::is_valid_pattern(pool, pattern).await {
+            ctx.send_simple(
+                true,
+                "Invalid regular expression",
+                Some(&format!(
+                    "``{}`` is not a valid regular expression.",
+                    pattern
+                )),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let count = message_cache::count_messages(pool, &filter).await?;
+    if count == 0 {
+        ctx.send_simple(
+            true,
+            "No messages found",
+            Some("No cached messages match the search."),
+            ctx.data().colors.input_warn().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    let color = ctx.data().colors.mod_message_log().await;
+
+    if export.unwrap_or(false) {
+        // Write the messages in batches until they run out or the file is full
+        let mut text = String::new();
+        let mut exported = 0;
+        loop {
+            let messages = message_cache::fetch_messages(
+                pool,
+                &filter,
+                exported as i64,
+                Some(MESSAGE_LOG_EXPORT_BATCH),
+            )
+            .await?;
+            let (batch, written) =
+                message_cache::export_text(&messages, MESSAGE_LOG_EXPORT_SIZE - text.len());
+            text.push_str(&batch);
+            exported += written;
+            if written < messages.len() || messages.len() < MESSAGE_LOG_EXPORT_BATCH as usize {
+                break;
+            }
+        }
+
+        let description = if (exported as i64) < count {
+            format!(
+                "Exported the newest {} of {} matching messages, the rest didn't fit into the file.",
+                exported, count
+            )
+        } else {
+            format!("Exported {} matching messages.", count)
+        };
+        ctx.send(|m| {
+            m.embed(|e| {
+                e.title("Message log export")
+                    .description(description)
+                    .color(color)
+            })
+            .attachment(AttachmentType::Bytes {
+                data: Cow::from(text.into_bytes()),
+                filename: "messages.txt".to_string(),
+            })
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // Jump links need the guild of the channel
+    let guild_id = ctx.guild_id().unwrap();
+    let pages = (count + MESSAGE_LOG_PAGE_SIZE - 1) / MESSAGE_LOG_PAGE_SIZE;
+    pagination::paginate(ctx, pages as usize, true, |page| {
+        message_log_page(pool, &filter, guild_id, count, page as i64, pages, color)
+    })
+    .await
+}
+
+/// Get the channels and threads a member can read the history of, leaving out the ones blacklisted
+/// from the message logs. Only active threads are known, so archived ones are left out as well.
+fn readable_channels(guild: &Guild, member: &Member, blacklist: &[i64]) -> Vec<i64> {
+    let can_read = |channel: &GuildChannel| {
+        guild
+            .user_permissions_in(channel, member)
+            .map_or(false, |permissions| {
+                permissions.view_channel() && permissions.read_message_history()
+            })
+    };
+
+    // Threads don't have permissions of their own, they use the ones of their parent channel
+    let threads = guild.threads.iter().filter(|thread| {
+        match thread
+            .parent_id
+            .and_then(|parent_id| guild.channels.get(&parent_id))
+        {
+            Some(Channel::Guild(parent)) => can_read(parent),
+            _ => false,
+        }
+    });
+    guild
+        .channels
+        .values()
+        .filter_map(|channel| match channel {
+            Channel::Guild(channel) if can_read(channel) => Some(channel),
+            _ => None,
+        })
+        .chain(threads)
+        .map(|channel| channel.id.0 as i64)
+        .filter(|channel_id| !blacklist.contains(channel_id))
+        .collect()
+}
+
+/// Build the embed for a page of the message log search results
+async fn message_log_page(
+    pool: &PgPool,
+    filter: &MessageFilter,
+    guild_id: GuildId,
+    count: i64,
+    page: i64,
+    pages: i64,
+    color: Color,
+) -> Result<CreateEmbed, Error> {
+    let messages = message_cache::fetch_messages(
+        pool,
+        filter,
+        page * MESSAGE_LOG_PAGE_SIZE,
+        Some(MESSAGE_LOG_PAGE_SIZE),
+    )
+    .await?;
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("Message log ({} messages)", count))
+        .color(color)
+        .footer(|f| f.text(format!("Page {}/{}", page + 1, pages)));
+    for msg in messages {
+        let name = match (msg.deleted_at, msg.edited_at) {
+            (Some(_), _) => "Deleted message",
+            (None, Some(_)) => "Edited message",
+            (None, None) => "Message",
+        };
+        let mut value = format!(
+            "<@{}> in <#{}> at <t:{}:f>\n",
+            msg.user_id,
+            msg.channel_id,
+            msg.message_time.timestamp()
+        );
+        if msg.content.is_empty() {
+            value.push_str("*No content*");
+        } else {
+            value.push_str(&shorten(&msg.content, MESSAGE_LOG_CONTENT_LENGTH));
+        }
+        if !msg.attachments.is_empty() {
+            value.push_str(&format!(
+                "\n{} attachment(s)",
+                msg.attachments.split(' ').count()
+            ));
+        }
+        // Deleted messages can't be jumped to anymore
+        if msg.deleted_at.is_none() {
+            value.push_str(&format!(
+                "\n[Jump to message](https://discord.com/channels/{}/{}/{})",
+                guild_id, msg.channel_id, msg.message_id
+            ));
+        }
+        embed.field(name, value, false);
+    }
+    Ok(embed)
+}
@@ -1,10 +1,30 @@
 use crate::{
     command_error,
     traits::{context_ext::ContextExt, readable::Readable},
+    types::data::Data,
+    utils::{helper_functions::shorten, pagination},
     Context, Error,
 };
-use chrono::{DateTime, Utc};
-use poise::{serenity_prelude::CreateEmbed, CreateReply};
+use chrono::{DateTime, Duration, Utc};
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, Member, MessageId, RoleId, User, UserId,
+};
+use sqlx::PgPool;
+use std::future;
+
+// The most characters of the description and system info shown in a ticket embed, to keep the
+// search results within the embed size limits
+const TICKET_EMBED_TEXT_LENGTH: usize = 300;
+// The most tickets found by a search
+const SEARCH_RESULT_LIMIT: i64 = 100;
+// How many tickets are shown on a page of the search results
+const SEARCH_PAGE_SIZE: usize = 5;
+// The most characters of the description shown in the search results
+const SEARCH_DESCRIPTION_LENGTH: usize = 150;
+// The most characters in the name of a thread
+const THREAD_NAME_LENGTH: usize = 100;
+// The name of the field showing the assignee on the ticket summary
+const ASSIGNEE_FIELD: &str = "Assigned to";
 
 // ----------------------------
 // Support thread related types
@@ -19,6 +39,20 @@ pub struct SupportThread {
     pub incident_title: String,
     pub incident_solved: bool,
     pub unarchivals: i16,
+    // Not known for old tickets until they are backfilled from their starter messages
+    pub incident_description: Option<String>,
+    pub incident_system_info: Option<String>,
+    // The helper who claimed the ticket or was assigned to it
+    pub assignee_id: Option<i64>,
+}
+
+/// The filters of a ticket search, every filter that is set has to match
+struct TicketFilter {
+    query: Option<String>,
+    solved: Option<bool>,
+    user_id: Option<i64>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug)]
@@ -53,7 +87,7 @@ pub async fn solve(ctx: Context<'_>) -> Result<(), Error> {
     let pool = &*ctx.data().pool;
 
     // Get the row with the current channel id from the database
-    let thread = match sqlx::query_as!(
+    let mut thread = match sqlx::query_as!(
         SupportThread,
         r#"SELECT * FROM ttc_support_tickets WHERE thread_id = $1"#,
         ctx.channel_id().0 as i64
@@ -101,12 +135,8 @@ pub async fn solve(ctx: Context<'_>) -> Result<(), Error> {
     )
     .await?;
 
-    let mut new_thread_name = format!(
-        "[SOLVED] [{}] {}",
-        thread.incident_id, thread.incident_title
-    );
-    // Make sure the channel name fits in discord channel name character limits
-    new_thread_name.truncate(100);
+    thread.incident_solved = true;
+    let new_thread_name = ticket_thread_name(&thread);
 
     // Archive the thread after getting the solution
     ctx.channel_id()
@@ -118,69 +148,134 @@ pub async fn solve(ctx: Context<'_>) -> Result<(), Error> {
 
 /// Search for a support ticket
 ///
-/// Search for a support ticket based on either title, id or both.
-/// **NOTE**: Either id or title must be provided.
-/// ``search [id (optional)] [title (optional)]``
+/// Search for a support ticket by id, or through the titles, descriptions and system info of the \
+/// tickets, best matches first. The text search can be narrowed down with the filters.
+/// **NOTE**: Either id, query or a filter must be provided.
+/// ``search [id (optional)] [query (optional)] [solved (optional)] [author (optional)] [since (optional)] [until (optional)]``
+///
+/// ``since`` and ``until`` are human-readable strings of how long ago, like \
+/// ``30d``
 #[poise::command(slash_command, prefix_command, category = "Support")]
 pub async fn search(
     ctx: Context<'_>,
     #[description = "Id to search for"]
     #[min = 0]
     id: Option<u32>,
-    #[description = "Title to search for"] title: Option<String>,
+    #[description = "Words to search the tickets for"] query: Option<String>,
+    #[description = "Only show solved or unsolved tickets"] solved: Option<bool>,
+    #[description = "Only show tickets created by this user"] author: Option<User>,
+    #[description = "Only show tickets from this long ago onwards, for example 30d"]
+    #[rename = "since"]
+    since_str: Option<String>,
+    #[description = "Only show tickets from up to this long ago, for example 7d"]
+    #[rename = "until"]
+    until_str: Option<String>,
 ) -> Result<(), Error> {
-    // Let's keep count so we know if one of them was around
-    let mut options_used = 0;
-
     if let Some(id) = id {
         search_id(ctx, id).await?;
-        options_used += 1;
-    }
-    if let Some(title) = title {
-        search_title(ctx, title).await?;
-        options_used += 1;
     }
-    if options_used == 0 {
+
+    let ago = |duration_str: Option<String>| -> Result<Option<DateTime<Utc>>, Error> {
+        match duration_str {
+            Some(duration_str) => {
+                let duration = humantime::parse_duration(&duration_str)?;
+                Ok(Some(Utc::now() - Duration::from_std(duration)?))
+            }
+            None => Ok(None),
+        }
+    };
+    let filter = TicketFilter {
+        query,
+        solved,
+        user_id: author.map(|author| author.id.0 as i64),
+        since: ago(since_str)?,
+        until: ago(until_str)?,
+    };
+
+    if filter.query.is_some()
+        || filter.solved.is_some()
+        || filter.user_id.is_some()
+        || filter.since.is_some()
+        || filter.until.is_some()
+    {
+        search_text(ctx, filter).await?;
+    } else if id.is_none() {
         return Err(Error::from(
-            "Please provide either an id or a title to search for.",
+            "Please provide either an id, a query or a filter to search with.",
         ));
     }
 
     Ok(())
 }
 
-async fn search_title(ctx: Context<'_>, title: String) -> Result<(), Error> {
+async fn search_text(ctx: Context<'_>, filter: TicketFilter) -> Result<(), Error> {
     let pool = &*ctx.data().pool;
 
-    // Loop through the arguments and with each iteration search for them from the database, if
-    // found send a message with the information about the ticket
+    // The weighted document has to match the expression of the search index, without a query
+    // the tickets are listed newest first
     let threads = sqlx::query_as!(
         SupportThread,
-        r#"SELECT * FROM ttc_support_tickets WHERE incident_title LIKE CONCAT('%', $1::text, '%')"#,
-        title
+        r#"SELECT * FROM ttc_support_tickets
+        WHERE ($1::text IS NULL OR (
+            setweight(to_tsvector('english', incident_title), 'A')
+            || setweight(to_tsvector('english', coalesce(incident_description, '')), 'B')
+            || setweight(to_tsvector('english', coalesce(incident_system_info, '')), 'C')
+        ) @@ websearch_to_tsquery('english', $1))
+        AND ($2::bool IS NULL OR incident_solved = $2) AND ($3::int8 IS NULL OR user_id = $3)
+        AND ($4::timestamptz IS NULL OR incident_time >= $4) AND ($5::timestamptz IS NULL OR incident_time <= $5)
+        ORDER BY ts_rank(
+            setweight(to_tsvector('english', incident_title), 'A')
+            || setweight(to_tsvector('english', coalesce(incident_description, '')), 'B')
+            || setweight(to_tsvector('english', coalesce(incident_system_info, '')), 'C'),
+            websearch_to_tsquery('english', $1)
+        ) DESC NULLS LAST, incident_time DESC
+        LIMIT $6"#,
+        filter.query,
+        filter.solved,
+        filter.user_id,
+        filter.since,
+        filter.until,
+        SEARCH_RESULT_LIMIT
     )
     .fetch_all(pool)
     .await?;
 
-    let mut msg = if !threads.is_empty() {
-        let color = ctx.data().colors.support_info().await;
-        let mut msg = CreateReply::default();
-        msg.embed(|e| e.title("List of support tickets found:").color(color));
-
-        for thread in &threads {
-            msg.embed(|e| support_ticket_embed(thread, e));
-        }
-        msg
-    } else {
+    if threads.is_empty() {
         let color = ctx.data().colors.general_error().await;
-        let mut msg = CreateReply::default();
-        msg.embed(|e| e.title("No support tickets found.").color(color));
-        msg
-    };
-
-    ctx.send(|_| &mut msg).await?;
+        ctx.send(|m| m.embed(|e| e.title("No support tickets found.").color(color)))
+            .await?;
+        return Ok(());
+    }
 
-    Ok(())
+    // Create the pages of the results to be cycled through
+    let color = ctx.data().colors.support_info().await;
+    let pages = threads.chunks(SEARCH_PAGE_SIZE).count();
+    let embed_vec = threads
+        .chunks(SEARCH_PAGE_SIZE)
+        .enumerate()
+        .map(|(index, page)| {
+            let mut embed = CreateEmbed::default();
+            embed
+                .title("List of support tickets found:")
+                .color(color)
+                .footer(|f| {
+                    f.text(format!(
+                        "Page {}/{} - {} tickets",
+                        index + 1,
+                        pages,
+                        threads.len()
+                    ))
+                });
+            for thread in page {
+                support_ticket_field(thread, &mut embed);
+            }
+            embed
+        })
+        .collect::<Vec<CreateEmbed>>();
+    pagination::paginate(ctx, embed_vec.len(), false, |page| {
+        future::ready(Ok(embed_vec[page].clone()))
+    })
+    .await
 }
 
 async fn search_id(ctx: Context<'_>, id: u32) -> Result<(), Error> {
@@ -214,6 +309,165 @@ async fn search_id(ctx: Context<'_>, id: u32) -> Result<(), Error> {
     Ok(())
 }
 
+/// Manage the helper of the current support ticket
+///
+/// Commands to assign a helper to the current support ticket or to remove the assignment
+/// **NOTE**: These commands are only available in support threads.
+/// ``ticket [assign|unclaim]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "is_in_support_thread",
+    category = "Support",
+    guild_only,
+    subcommands("ticket_assign", "ticket_unclaim")
+)]
+pub async fn ticket(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Assign a helper to the current support ticket
+///
+/// Makes a helper responsible for the current support ticket, replacing the previous assignee.
+/// **NOTE**: Only helpers can assign tickets.
+/// ``ticket assign [helper]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "is_in_support_thread",
+    category = "Support",
+    guild_only,
+    rename = "assign"
+)]
+pub async fn ticket_assign(
+    ctx: Context<'_>,
+    #[description = "The helper to assign the ticket to"] helper: Member,
+) -> Result<(), Error> {
+    let author = ctx
+        .author_member()
+        .await
+        .ok_or(Error::from("Failed to get member"))?;
+    if !is_support_helper(&author, ctx.data()).await? {
+        ctx.send_simple(
+            true,
+            "Not a helper",
+            Some("Only helpers can assign support tickets."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    if !is_support_helper(&helper, ctx.data()).await? {
+        ctx.send_simple(
+            true,
+            "Not a helper",
+            Some(&format!("<@{}> is not a helper.", helper.user.id)),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let thread = match assign_ticket(&ctx.data().pool, ctx.channel_id(), helper.user.id).await? {
+        Some(thread) => thread,
+        None => {
+            ctx.send_simple(
+                true,
+                "Ticket solved",
+                Some("This ticket has already been solved and can't be assigned."),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    ctx.send_simple(
+        false,
+        "Ticket assigned",
+        Some(&format!(
+            "<@{}> has been assigned to this ticket.",
+            helper.user.id
+        )),
+        ctx.data().colors.support_info().await,
+    )
+    .await?;
+    show_ticket_assignee(ctx.serenity_context(), &thread).await;
+
+    Ok(())
+}
+
+/// Remove the helper of the current support ticket
+///
+/// Makes the current support ticket unclaimed again so another helper can claim it.
+/// **NOTE**: Only the assigned helper and moderators can unclaim a ticket.
+/// ``ticket unclaim``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "is_in_support_thread",
+    category = "Support",
+    guild_only,
+    rename = "unclaim"
+)]
+pub async fn ticket_unclaim(ctx: Context<'_>) -> Result<(), Error> {
+    let thread = sqlx::query_as!(
+        SupportThread,
+        r#"SELECT * FROM ttc_support_tickets WHERE thread_id = $1"#,
+        ctx.channel_id().0 as i64
+    )
+    .fetch_one(&*ctx.data().pool)
+    .await?;
+    // Solved tickets are archived and keep their helper
+    if thread.incident_solved {
+        ctx.send_simple(
+            true,
+            "Ticket solved",
+            Some("This ticket has already been solved and can't be unclaimed."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    if thread.assignee_id.is_none() {
+        ctx.send_simple(
+            true,
+            "Not claimed",
+            Some("This ticket hasn't been claimed."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let moderator_role = RoleId(ctx.data().config.moderator_role().await? as u64);
+    let is_moderator = ctx
+        .author_member()
+        .await
+        .map_or(false, |member| member.roles.contains(&moderator_role));
+    if thread.assignee_id != Some(ctx.author().id.0 as i64) && !is_moderator {
+        ctx.send_simple(
+            true,
+            "Not your ticket",
+            Some("Only the assigned helper and moderators can unclaim this ticket."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let thread = unassign_ticket(&ctx.data().pool, ctx.channel_id()).await?;
+    ctx.send_simple(
+        false,
+        "Ticket unclaimed",
+        Some("This ticket can be claimed by another helper now."),
+        ctx.data().colors.support_info().await,
+    )
+    .await?;
+    show_ticket_assignee(ctx.serenity_context(), &thread).await;
+
+    Ok(())
+}
+
 // Check for making sure command originated from one of the known support threads in the database
 async fn is_in_support_thread(ctx: Context<'_>) -> Result<bool, Error> {
     let pool = &*ctx.data().pool;
@@ -237,13 +491,185 @@ async fn is_in_support_thread(ctx: Context<'_>) -> Result<bool, Error> {
 // Support group related event handling
 // ------------------------------------
 
+/// The name of a support thread, showing the ticket id and whether it's solved or claimed
+pub fn ticket_thread_name(thread: &SupportThread) -> String {
+    let name = if thread.incident_solved {
+        format!(
+            "[SOLVED] [{}] {}",
+            thread.incident_id, thread.incident_title
+        )
+    } else if thread.assignee_id.is_some() {
+        format!(
+            "[CLAIMED] [{}] {}",
+            thread.incident_id, thread.incident_title
+        )
+    } else {
+        format!("[{}] {}", thread.incident_id, thread.incident_title)
+    };
+    // Make sure the channel name fits in discord channel name character limits
+    name.chars().take(THREAD_NAME_LENGTH).collect()
+}
+
+/// Check whether a member can claim and be assigned support tickets, moderators always can
+pub async fn is_support_helper(member: &Member, data: &Data) -> Result<bool, Error> {
+    let mut roles = vec![RoleId(data.config.moderator_role().await? as u64)];
+    roles.extend(
+        data.config
+            .support_helper_role()
+            .await?
+            .map(|role| RoleId(role as u64)),
+    );
+    Ok(member.roles.iter().any(|role| roles.contains(role)))
+}
+
+/// Assign an unsolved ticket to a helper, replacing the current assignee. Returns `None` if the
+/// ticket has been solved.
+pub async fn assign_ticket(
+    pool: &PgPool,
+    thread_id: ChannelId,
+    assignee: UserId,
+) -> Result<Option<SupportThread>, Error> {
+    Ok(sqlx::query_as!(
+        SupportThread,
+        r#"UPDATE ttc_support_tickets SET assignee_id = $1 WHERE thread_id = $2 AND NOT incident_solved RETURNING *"#,
+        assignee.0 as i64,
+        thread_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// Remove the assignee of a support ticket, returns the updated ticket
+pub async fn unassign_ticket(pool: &PgPool, thread_id: ChannelId) -> Result<SupportThread, Error> {
+    Ok(sqlx::query_as!(
+        SupportThread,
+        r#"UPDATE ttc_support_tickets SET assignee_id = NULL WHERE thread_id = $1 RETURNING *"#,
+        thread_id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?)
+}
+
+/// Claim an unclaimed and unsolved ticket. Returns `None` if someone else got to it first, the
+/// check is part of the update so two helpers can't claim the same ticket at once.
+pub async fn claim_ticket(
+    pool: &PgPool,
+    thread_id: ChannelId,
+    assignee: UserId,
+) -> Result<Option<SupportThread>, Error> {
+    Ok(sqlx::query_as!(
+        SupportThread,
+        r#"UPDATE ttc_support_tickets SET assignee_id = $1 WHERE thread_id = $2 AND assignee_id IS NULL AND NOT incident_solved RETURNING *"#,
+        assignee.0 as i64,
+        thread_id.0 as i64
+    )
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// Get the summary message of a ticket. The thread was started from the summary message, so they
+/// share the same id.
+pub async fn ticket_summary_message(
+    ctx: &serenity::Context,
+    thread_id: ChannelId,
+) -> Result<serenity::Message, Error> {
+    let parent_id = match thread_id.to_channel(ctx).await?.guild() {
+        Some(thread) => match thread.parent_id {
+            Some(parent_id) => parent_id,
+            None => return command_error!("Support thread {} has no parent channel", thread_id),
+        },
+        None => return command_error!("Support thread {} is not a guild channel", thread_id),
+    };
+    Ok(parent_id.message(ctx, MessageId(thread_id.0)).await?)
+}
+
+/// Show the assignee of a ticket in the thread name and on the ticket summary. Discord limits how
+/// often threads can be renamed, so this is done after responding and failures are only logged.
+pub async fn show_ticket_assignee(ctx: &serenity::Context, thread: &SupportThread) {
+    let thread_id = ChannelId(thread.thread_id as u64);
+    if let Err(why) = thread_id
+        .edit_thread(ctx, |t| t.name(ticket_thread_name(thread)))
+        .await
+    {
+        log::warn!("Unable to rename support thread: {}", why);
+    }
+
+    let mut summary = match ticket_summary_message(ctx, thread_id).await {
+        Ok(summary) => summary,
+        Err(why) => {
+            log::warn!("Unable to get support ticket summary: {}", why);
+            return;
+        }
+    };
+    // Replace the previous assignee instead of adding another one
+    let mut embed = match summary.embeds.first() {
+        Some(embed) => embed.clone(),
+        None => return,
+    };
+    embed.fields.retain(|field| field.name != ASSIGNEE_FIELD);
+    let mut new_embed = CreateEmbed::from(embed);
+    if let Some(assignee_id) = thread.assignee_id {
+        new_embed.field(ASSIGNEE_FIELD, format!("<@{}>", assignee_id), false);
+    }
+    if let Err(why) = summary.edit(ctx, |m| m.set_embed(new_embed)).await {
+        log::warn!("Unable to update support ticket summary: {}", why);
+    }
+}
+
+/// Add a ticket as a field to a page of the search results
+fn support_ticket_field(thread: &SupportThread, embed: &mut CreateEmbed) {
+    let mut value = format!(
+        "{} - <@{}> - {}\n<#{}>",
+        if thread.incident_solved {
+            "Solved"
+        } else {
+            "Unsolved"
+        },
+        thread.user_id,
+        thread.incident_time.readable(),
+        thread.thread_id
+    );
+    if let Some(assignee_id) = thread.assignee_id {
+        value.push_str(&format!("\nAssigned to <@{}>", assignee_id));
+    }
+    if let Some(description) = &thread.incident_description {
+        value.push_str(&format!(
+            "\n{}",
+            shorten(description, SEARCH_DESCRIPTION_LENGTH)
+        ));
+    }
+    embed.field(
+        format!("[{}] {}", thread.incident_id, thread.incident_title),
+        value,
+        false,
+    );
+}
+
 fn support_ticket_embed<'a>(
     thread: &SupportThread,
     embed: &'a mut CreateEmbed,
 ) -> &'a mut CreateEmbed {
     embed
         .title(format!("Support ticket [{}]", thread.incident_id))
-        .field("Title:", thread.incident_title.clone(), false)
+        .field("Title:", thread.incident_title.clone(), false);
+    if let Some(description) = &thread.incident_description {
+        embed.field(
+            "Description:",
+            shorten(description, TICKET_EMBED_TEXT_LENGTH),
+            false,
+        );
+    }
+    if let Some(system_info) = &thread.incident_system_info {
+        embed.field(
+            "System info:",
+            shorten(system_info, TICKET_EMBED_TEXT_LENGTH),
+            false,
+        );
+    }
+    if let Some(assignee_id) = thread.assignee_id {
+        embed.field("Assignee:", format!("<@{}>", assignee_id), false);
+    }
+    embed
         .field(
             "Status:",
             format!("Solved: {}", thread.incident_solved,),
@@ -252,3 +678,44 @@ fn support_ticket_embed<'a>(
         .field("Timestamp:", thread.incident_time.readable(), false)
         .field("Thread:", format!("<#{}>", thread.thread_id), false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_names() {
+        let mut thread = SupportThread {
+            incident_id: 12,
+            thread_id: 1,
+            user_id: 1,
+            incident_time: Utc::now(),
+            incident_title: "Computer does not turn on".to_string(),
+            incident_solved: false,
+            unarchivals: 0,
+            incident_description: None,
+            incident_system_info: None,
+            assignee_id: None,
+        };
+        assert_eq!(
+            ticket_thread_name(&thread),
+            "[12] Computer does not turn on"
+        );
+
+        thread.assignee_id = Some(2);
+        assert_eq!(
+            ticket_thread_name(&thread),
+            "[CLAIMED] [12] Computer does not turn on"
+        );
+
+        // Solving takes precedence over the claim
+        thread.incident_solved = true;
+        assert_eq!(
+            ticket_thread_name(&thread),
+            "[SOLVED] [12] Computer does not turn on"
+        );
+
+        thread.incident_title = "ä".repeat(200);
+        assert_eq!(ticket_thread_name(&thread).chars().count(), 100);
+    }
+}
@@ -1,93 +1,97 @@
 use crate::{
-    traits::readable::Readable, types::data::Data, unwrap_or_return,
-    utils::helper_functions::is_user_timed_out,
+    events::{ghost_ping, invites},
+    traits::readable::Readable,
+    types::{data::Data, event_type::EventType, log_category::LogCategory},
+    unwrap_or_return,
+    utils::{
+        attachment_archive,
+        audit_log::{self, Attribution},
+        diff, event_store,
+        helper_functions::{is_user_timed_out, shorten},
+        impersonation, message_cache,
+    },
 };
 use chrono::{DateTime, Utc};
 use poise::serenity_prelude::*;
+use serde_json::json;
+use std::{borrow::Cow, sync::Arc};
 
 // Types for fetching/writing data from/to SQL database
-struct CurrentIndex {
-    current_id: i32,
-}
-
 #[allow(dead_code)] // A few of these parameters are technically never read, but it is best that they are available in case they are needed
 struct CachedMessage {
-    id: i32,
-    message_id: Option<i64>,
-    channel_id: Option<i64>,
-    user_id: Option<i64>,
-    message_time: Option<DateTime<Utc>>,
-    content: Option<String>,
-    attachments: Option<String>,
+    message_id: i64,
+    channel_id: i64,
+    user_id: i64,
+    message_time: DateTime<Utc>,
+    content: String,
+    attachments: String,
+    mentioned_users: Vec<i64>,
+    mentioned_roles: Vec<i64>,
+    edited_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
 }
 
 // --------------------------------
 // Functions for conveyance logging
 // --------------------------------
 
-// Store 500 most recent messages seen by this bot in a cache for informing when it had been
-// deleted
+// Store the messages seen by this bot in a cache for informing when they have been deleted or
+// edited, old messages are cleaned up by the message cache cleanup task
 pub async fn message(ctx: &Context, msg: &Message, data: &Data) {
-    let pool = &*data.pool;
-
-    let mut id = unwrap_or_return!(
-        sqlx::query_as!(
-            CurrentIndex,
-            r#"SELECT current_message_id AS current_id FROM ttc_conveyance_state"#
-        )
-        .fetch_one(pool)
-        .await,
-        "Reading from database failed"
-    );
-
-    // Set the id to 0 to loop thru the message cache after reaching the end of the cache
-    if id.current_id >= 500 {
-        id.current_id = 0;
+    // Archive the attachments in the background, the logs of the bot and the channels blacklisted
+    // from the message logs don't need archiving since their deletions are never logged
+    if !msg.attachments.is_empty() && msg.author.id != ctx.cache.current_user_id() {
+        let blacklisted = match data
+            .config
+            .log_blacklist_channels(LogCategory::Messages)
+            .await
+        {
+            Ok(channels) => channels.contains(&(msg.channel_id.0 as i64)),
+            Err(why) => {
+                log::error!("Error getting blacklisted log channels: {}", why);
+                false
+            }
+        };
+        if !blacklisted {
+            tokio::spawn(attachment_archive::archive_attachments(
+                Arc::clone(&data.pool),
+                msg.id,
+                msg.attachments.clone(),
+            ));
+        }
     }
-    // Increment the id to move over to a new entry in the cache
-    id.current_id += 1;
+
+    let pool = &*data.pool;
 
     // Write the message contents to the cache
     unwrap_or_return!(sqlx::query!(
-        r#"UPDATE ttc_message_cache SET message_id = $1, channel_id = $2, user_id = $3, message_time = $4, content = $5, attachments = $6 WHERE id = $7"#, 
+        r#"INSERT INTO ttc_message_cache (message_id, channel_id, user_id, message_time, content, attachments, mentioned_users, mentioned_roles) VALUES($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (message_id) DO NOTHING"#,
         msg.id.0 as i64,
         msg.channel_id.0 as i64,
         msg.author.id.0 as i64,
         Utc::now(),
         msg.content_safe(ctx),
         msg.attachments.iter().map(|a| a.url.clone()).collect::<Vec<String>>().join(" "),
-        id.current_id
+        &msg.mentions.iter().map(|user| user.id.0 as i64).collect::<Vec<i64>>(),
+        &msg.mention_roles.iter().map(|role| role.0 as i64).collect::<Vec<i64>>(),
     )
     .execute(pool)
     .await, "Writing to database failed");
-
-    unwrap_or_return!(
-        sqlx::query!(
-            r#"UPDATE ttc_conveyance_state SET current_message_id = $1"#,
-            id.current_id
-        )
-        .execute(pool)
-        .await,
-        "Writing to database failed"
-    );
 }
 
 // Send logging messages when messages are deleted
 pub async fn message_delete(
     ctx: &Context,
+    guild_id: &Option<GuildId>,
     channel_id: &ChannelId,
     deleted_message_id: &MessageId,
     data: &Data,
 ) {
-    // Make sure the channel isn't blacklisted from conveyance
-    if unwrap_or_return!(
-        data.config.conveyance_blacklist_channel().await,
-        "Error getting conveyance blacklisted channels"
-    )
-    .contains(&(channel_id.0 as i64))
-    {
-        return;
-    }
+    // Deleted messages stay in the cache until they are cleaned up, so moderators can search them
+    message_cache::mark_deleted(&data.pool, &[deleted_message_id.0 as i64]).await;
+    let ghost_ping =
+        ghost_ping::message_delete(ctx, guild_id, channel_id, deleted_message_id, data).await;
+
     let pool = &*data.pool;
 
     // Get the cached message from the database
@@ -112,8 +116,50 @@ pub async fn message_delete(
         }
     };
 
+    let event_id = event_store::record_event(
+        data,
+        *guild_id,
+        UserId(msg.user_id as u64),
+        EventType::MessageDelete,
+        json!({
+            "message_id": msg.message_id.to_string(),
+            "channel_id": msg.channel_id.to_string(),
+            "sent_at": msg.message_time.timestamp(),
+            "content": msg.content,
+            "attachments": msg.attachments,
+            "bulk": false,
+        }),
+    )
+    .await;
+
+    // The blacklist only keeps the message out of the log channels, it is still stored as an
+    // event and ghost pings are still noticed
+    if unwrap_or_return!(
+        data.config.log_blacklist_channels(LogCategory::Messages).await,
+        "Error getting blacklisted log channels"
+    )
+    .contains(&(channel_id.0 as i64))
+    {
+        unwrap_or_return!(
+            attachment_archive::remove_archived_attachments(pool, &[msg.message_id]).await,
+            "Error removing archived attachments"
+        );
+        if let Some(ghost_ping) = ghost_ping {
+            match find_delete_attribution(ctx, guild_id, channel_id, ghost_ping.author, data).await
+            {
+                Some(attribution) => {
+                    if let Some(event_id) = event_id {
+                        event_store::add_event_attribution(data, event_id, &attribution).await;
+                    }
+                }
+                None => ghost_ping::send_notice(ctx, data, *channel_id, ghost_ping).await,
+            }
+        }
+        return;
+    }
+
     // Get the user from either cache or rest api
-    let user = match UserId(msg.user_id.unwrap() as u64).to_user(ctx).await {
+    let user = match UserId(msg.user_id as u64).to_user(ctx).await {
         Ok(user) => user,
         Err(why) => {
             log::warn!("Error getting user based on user id: {}", why);
@@ -122,64 +168,403 @@ pub async fn message_delete(
     };
     // Make sure both content and attachment strings are not empty as being empty would cause
     // errors when sending the embed
-    let mut content = if msg.content.as_ref().unwrap() == "" {
+    let content = if msg.content.is_empty() {
         "None".to_string()
     } else {
-        msg.content.unwrap()
+        shorten(&msg.content, 1021)
     };
-    let mut attachments = if msg.attachments.as_ref().unwrap() == "" {
+    let attachments = if msg.attachments.is_empty() {
         "None".to_string()
     } else {
-        msg.attachments.unwrap()
+        shorten(&msg.attachments, 1021)
     };
 
-    content.truncate(1024);
-    attachments.truncate(1024);
+    // The links to the attachments stop working once the message is deleted, so upload the
+    // archived copies with the log instead
+    let (archived, not_uploaded) = match attachment_archive::load_archived_attachments(
+        pool,
+        &[msg.message_id],
+        10,
+        attachment_archive::upload_limit(ctx, *guild_id),
+    )
+    .await
+    {
+        Ok(archived) => archived,
+        Err(why) => {
+            log::error!("Error loading archived attachments: {}", why);
+            (Vec::new(), Vec::new())
+        }
+    };
+    let archived_string = if not_uploaded.is_empty() {
+        format!("{} attached to this log", archived.len())
+    } else {
+        format!(
+            "{} attached to this log, too large to upload: {}",
+            archived.len(),
+            not_uploaded.join(", ")
+        )
+    };
+    // The file names can contain any characters, so shorten by characters to stay on a boundary
+    let archived_string = shorten(&archived_string, 1021);
+    // Used when the upload fails, the log still goes out with the names of the attachments
+    let failed_string = shorten(
+        &format!(
+            "Failed to upload: {}",
+            archived
+                .iter()
+                .map(|attachment| attachment.filename.as_str())
+                .chain(not_uploaded.iter().map(|filename| filename.as_str()))
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+        1021,
+    );
 
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::Messages).await,
+        "Error getting log channels"
     );
 
-    let color = data.colors.conveyance_msg_delete().await;
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Message deleted")
+        .color(data.colors.conveyance_msg_delete().await)
+        .field("User", user.tag(), true)
+        .field("UserId", user.id, true)
+        .field("Message sent at", msg.message_time.readable(), false)
+        .field("Channel", format!("<#{}>", msg.channel_id), true)
+        .field("Content", content, false)
+        .field("Attachments", attachments, false)
+        .timestamp(Utc::now());
+
+    let mut sent = Vec::new();
     for channel in &conv_channels {
-        unwrap_or_return!(
-            ChannelId(*channel as u64)
-                .send_message(ctx, |m| {
-                    m.embed(|e| {
-                        e.title("Message deleted")
-                            .color(color)
-                            .field("User", user.tag(), true)
-                            .field("UserId", user.id, true)
-                            .field(
-                                "Message sent at",
-                                match msg.message_time {
-                                    Some(time) => time.readable(),
-                                    None => "N/A".to_string(),
-                                },
-                                false,
-                            )
-                            .field("Channel", format!("<#{}>", msg.channel_id.unwrap()), true)
-                            .field("Content", content.clone(), false)
-                            .field("Attachments", attachments.clone(), false)
-                            .timestamp(Utc::now())
-                    })
-                })
-                .await,
-            "Failed to send message"
-        );
+        let channel = ChannelId(*channel as u64);
+        let mut archived_embed = embed.clone();
+        if !archived.is_empty() || !not_uploaded.is_empty() {
+            archived_embed.field("Archived attachments", archived_string.clone(), false);
+        }
+        let result = channel
+            .send_message(ctx, |m| {
+                m.set_embed(archived_embed);
+                for attachment in &archived {
+                    m.add_file(AttachmentType::Bytes {
+                        data: Cow::from(&attachment.data[..]),
+                        filename: attachment.filename.clone(),
+                    });
+                }
+                m
+            })
+            .await;
+
+        // A failed upload shouldn't lose the log, send it again with only the names of the files
+        let message = match result {
+            Ok(message) => message,
+            Err(why) if !archived.is_empty() => {
+                log::warn!(
+                    "Failed to upload archived attachments, sending the log without them: {}",
+                    why
+                );
+                let mut failed_embed = embed.clone();
+                failed_embed.field("Archived attachments", failed_string.clone(), false);
+                unwrap_or_return!(
+                    channel
+                        .send_message(ctx, |m| m.set_embed(failed_embed))
+                        .await,
+                    "Failed to send message"
+                )
+            }
+            Err(why) => {
+                log::error!("Failed to send message: {}", why);
+                return;
+            }
+        };
+        sent.push(message);
+    }
+
+    unwrap_or_return!(
+        attachment_archive::remove_archived_attachments(pool, &[msg.message_id]).await,
+        "Error removing archived attachments"
+    );
+
+    match find_delete_attribution(ctx, guild_id, channel_id, UserId(msg.user_id as u64), data).await
+    {
+        Some(attribution) => {
+            audit_log::add_attribution(ctx, sent, &attribution).await;
+            if let Some(event_id) = event_id {
+                event_store::add_event_attribution(data, event_id, &attribution).await;
+            }
+        }
+        // Nobody else deleted the message, so removing the mentions was up to the author
+        None => {
+            if let Some(ghost_ping) = ghost_ping {
+                ghost_ping::send_notice(ctx, data, *channel_id, ghost_ping).await;
+            }
+        }
     }
 }
 
+// Only messages deleted by someone other than their author show up in the audit log
+async fn find_delete_attribution(
+    ctx: &Context,
+    guild_id: &Option<GuildId>,
+    channel_id: &ChannelId,
+    author: UserId,
+    data: &Data,
+) -> Option<Attribution> {
+    audit_log::find_attribution(
+        ctx,
+        data,
+        (*guild_id)?,
+        Action::Message(MessageAction::Delete),
+        author.0,
+        Some(*channel_id),
+    )
+    .await
+}
+
+// Send a single summary with a transcript of the deleted messages when messages are bulk deleted
 pub async fn message_delete_bulk(
     ctx: &Context,
+    guild_id: &Option<GuildId>,
     channel_id: &ChannelId,
     deleted_message_ids: &Vec<MessageId>,
     data: &Data,
 ) {
-    for id in deleted_message_ids {
-        message_delete(ctx, channel_id, id, data).await;
+    let ids: Vec<i64> = deleted_message_ids.iter().map(|id| id.0 as i64).collect();
+    message_cache::mark_deleted(&data.pool, &ids).await;
+    let pool = &*data.pool;
+
+    // Get all of the cached messages at once, oldest first
+    let messages = unwrap_or_return!(
+        sqlx::query_as!(
+            CachedMessage,
+            r#"SELECT * FROM ttc_message_cache WHERE message_id = ANY($1) AND channel_id = $2 ORDER BY message_time, message_id"#,
+            &ids[..],
+            channel_id.0 as i64
+        )
+        .fetch_all(pool)
+        .await,
+        "Error reading messages from message cache database"
+    );
+    let uncached: Vec<i64> = ids
+        .iter()
+        .filter(|id| !messages.iter().any(|msg| msg.message_id == **id))
+        .copied()
+        .collect();
+
+    for msg in &messages {
+        event_store::record_event(
+            data,
+            *guild_id,
+            UserId(msg.user_id as u64),
+            EventType::MessageDelete,
+            json!({
+                "message_id": msg.message_id.to_string(),
+                "channel_id": msg.channel_id.to_string(),
+                "sent_at": msg.message_time.timestamp(),
+                "content": msg.content,
+                "attachments": msg.attachments,
+                "bulk": true,
+            }),
+        )
+        .await;
+    }
+
+    // The blacklist only keeps the messages out of the log channels, they are still stored as events
+    if unwrap_or_return!(
+        data.config.log_blacklist_channels(LogCategory::Messages).await,
+        "Error getting blacklisted log channels"
+    )
+    .contains(&(channel_id.0 as i64))
+    {
+        unwrap_or_return!(
+            attachment_archive::remove_archived_attachments(pool, &ids).await,
+            "Error removing archived attachments"
+        );
+        return;
+    }
+
+    // Get the tags of the authors from either cache or rest api
+    let mut authors: Vec<(i64, String)> = Vec::new();
+    for msg in &messages {
+        if authors.iter().any(|(id, _)| *id == msg.user_id) {
+            continue;
+        }
+        let tag = match UserId(msg.user_id as u64).to_user(ctx).await {
+            Ok(user) => user.tag(),
+            Err(why) => {
+                log::warn!("Error getting user based on user id: {}", why);
+                "Unknown user".to_string()
+            }
+        };
+        authors.push((msg.user_id, tag));
     }
+
+    // Upload as many of the archived attachments as fit next to the transcript
+    let (archived, not_uploaded) = match attachment_archive::load_archived_attachments(
+        pool,
+        &ids,
+        9,
+        attachment_archive::upload_limit(ctx, *guild_id),
+    )
+    .await
+    {
+        Ok(archived) => archived,
+        Err(why) => {
+            log::error!("Error loading archived attachments: {}", why);
+            (Vec::new(), Vec::new())
+        }
+    };
+
+    let transcript = bulk_delete_transcript(channel_id, &messages, &uncached, &authors);
+    let filename = format!("bulk-delete-{}-{}.txt", channel_id.0, Utc::now().timestamp());
+
+    let mut authors_string = if authors.is_empty() {
+        "None of the messages were cached".to_string()
+    } else {
+        authors
+            .iter()
+            .map(|(id, _)| format!("<@{}>", id))
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+    // Cut the list at the last whole mention that fits in the field
+    if authors_string.len() > 1024 {
+        authors_string.truncate(1020);
+        if let Some(index) = authors_string.rfind(' ') {
+            authors_string.truncate(index);
+        }
+        authors_string.push_str(" ...");
+    }
+
+    let conv_channels = unwrap_or_return!(
+        data.config.log_channels(LogCategory::Messages).await,
+        "Error getting log channels"
+    );
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Messages bulk deleted")
+        .color(data.colors.conveyance_msg_delete().await)
+        .field("Channel", format!("<#{}>", channel_id.0), true)
+        .field("Messages", deleted_message_ids.len(), true)
+        .field("Not cached", uncached.len(), true)
+        .field("Authors", authors_string, false)
+        .timestamp(Utc::now());
+
+    for channel in &conv_channels {
+        let channel = ChannelId(*channel as u64);
+        let mut archived_embed = embed.clone();
+        if !archived.is_empty() || !not_uploaded.is_empty() {
+            archived_embed.field(
+                "Archived attachments",
+                format!(
+                    "{} attached to this log, {} too large to upload",
+                    archived.len(),
+                    not_uploaded.len()
+                ),
+                false,
+            );
+        }
+        let result = channel
+            .send_message(ctx, |m| {
+                m.set_embed(archived_embed).add_file(AttachmentType::Bytes {
+                    data: Cow::from(transcript.as_bytes()),
+                    filename: filename.clone(),
+                });
+                for attachment in &archived {
+                    m.add_file(AttachmentType::Bytes {
+                        data: Cow::from(&attachment.data[..]),
+                        filename: attachment.filename.clone(),
+                    });
+                }
+                m
+            })
+            .await;
+
+        // A failed upload shouldn't lose the log, send it again with only the transcript
+        match result {
+            Ok(_) => (),
+            Err(why) if !archived.is_empty() => {
+                log::warn!(
+                    "Failed to upload archived attachments, sending the log without them: {}",
+                    why
+                );
+                let mut failed_embed = embed.clone();
+                failed_embed.field(
+                    "Archived attachments",
+                    format!(
+                        "Failed to upload {} attachments",
+                        archived.len() + not_uploaded.len()
+                    ),
+                    false,
+                );
+                unwrap_or_return!(
+                    channel
+                        .send_message(ctx, |m| {
+                            m.set_embed(failed_embed).add_file(AttachmentType::Bytes {
+                                data: Cow::from(transcript.as_bytes()),
+                                filename: filename.clone(),
+                            })
+                        })
+                        .await,
+                    "Failed to send message"
+                );
+            }
+            Err(why) => {
+                log::error!("Failed to send message: {}", why);
+                return;
+            }
+        }
+    }
+
+    unwrap_or_return!(
+        attachment_archive::remove_archived_attachments(pool, &ids).await,
+        "Error removing archived attachments"
+    );
+}
+
+// Write a plain text transcript of bulk deleted messages, listing the uncached ones by id
+fn bulk_delete_transcript(
+    channel_id: &ChannelId,
+    messages: &[CachedMessage],
+    uncached: &[i64],
+    authors: &[(i64, String)],
+) -> String {
+    let mut transcript = format!(
+        "{} messages deleted in channel {} at {}\n\n",
+        messages.len() + uncached.len(),
+        channel_id.0,
+        Utc::now().readable()
+    );
+
+    for msg in messages {
+        let author = authors
+            .iter()
+            .find(|(id, _)| *id == msg.user_id)
+            .map(|(_, tag)| tag.as_str())
+            .unwrap_or("Unknown user");
+        transcript.push_str(&format!(
+            "[{}] {} ({}): {}\n",
+            msg.message_time.readable(),
+            author,
+            msg.user_id,
+            msg.content
+        ));
+        if !msg.attachments.is_empty() {
+            transcript.push_str(&format!("    Attachments: {}\n", msg.attachments));
+        }
+    }
+
+    if !uncached.is_empty() {
+        transcript.push_str("\nMessages that were not cached:\n");
+        for id in uncached {
+            transcript.push_str(&format!("{}\n", id));
+        }
+    }
+
+    transcript
 }
 
 // Send logging messages when a message is edited
@@ -189,16 +574,6 @@ pub async fn message_update(
     event: &MessageUpdateEvent,
     data: &Data,
 ) {
-    // Make sure the channel isn't blacklisted from conveyance
-    if unwrap_or_return!(
-        data.config.conveyance_blacklist_channel().await,
-        "Error getting conveyance blacklisted channels"
-    )
-    .contains(&(event.channel_id.0 as i64))
-    {
-        return;
-    }
-
     let pool = &*data.pool;
 
     // Create the embed outside the closures to allow for async calls
@@ -224,7 +599,7 @@ pub async fn message_update(
     message_embed.field("Channel", format!("<#{}>", &event.channel_id.0), false);
 
     // Get the cached message from the database
-    let mut old_content = match sqlx::query_as!(
+    let (old_content, cached_user_id) = match sqlx::query_as!(
         CachedMessage,
         r#"SELECT * FROM ttc_message_cache WHERE message_id = $1 AND channel_id = $2"#,
         event.id.0 as i64,
@@ -233,16 +608,7 @@ pub async fn message_update(
     .fetch_one(pool)
     .await
     {
-        Ok(msg) => match msg.content {
-            Some(content) => {
-                if !content.is_empty() {
-                    content
-                } else {
-                    "None".to_string()
-                }
-            }
-            None => "Not available.".to_string(),
-        },
+        Ok(msg) => (Some(msg.content), Some(UserId(msg.user_id as u64))),
         Err(why) => {
             match why {
                 sqlx::Error::RowNotFound => {
@@ -250,14 +616,10 @@ pub async fn message_update(
                 }
                 _ => log::error!("Error reading message from message cache database: {}", why),
             }
-            "Not available.".to_string()
+            (None, None)
         }
     };
 
-    old_content.truncate(1024);
-
-    message_embed.field("Old", old_content, false);
-
     // Make sure the event is about the content being edited
     let new_content = match &event.content {
         Some(content) => {
@@ -265,37 +627,18 @@ pub async fn message_update(
             match new {
                 Some(new) => {
                     log::debug!("Edited message content got based on provided `new` argument");
-
-                    let mut content_safe = new.content_safe(ctx);
-                    content_safe.truncate(1024);
-                    if content_safe.is_empty() {
-                        content_safe = "None".to_string();
-                    }
-                    content_safe
+                    new.content_safe(ctx)
                 }
                 // Try to fetch the new message from the api
                 None => match event.channel_id.message(ctx, event.id).await {
                     Ok(new) => {
                         log::debug!("Edited message content got based on provided message got from the channel_id");
-
-                        let mut content_safe = new.content_safe(ctx);
-                        content_safe.truncate(1024);
-                        if content_safe.is_empty() {
-                            content_safe = "None".to_string();
-                        }
-                        content_safe
+                        new.content_safe(ctx)
                     }
                     // Fall back to the event in case all other methods fail
                     Err(why) => {
                         log::warn!("Error getting message: {}", why);
-
-                        let mut content_safe =
-                            content_safe(ctx, content, &ContentSafeOptions::default(), &[]);
-                        content_safe.truncate(1024);
-                        if content_safe.is_empty() {
-                            content_safe = "None".to_string();
-                        }
-                        content_safe
+                        content_safe(ctx, content, &ContentSafeOptions::default(), &[])
                     }
                 },
             }
@@ -305,12 +648,16 @@ pub async fn message_update(
         }
     };
 
-    message_embed.field("New", &new_content, false);
+    // Discord also sends updates when links get their embeds, those don't change the content
+    if old_content.as_ref() == Some(&new_content) {
+        return;
+    }
 
     unwrap_or_return!(
         sqlx::query!(
-            r#"UPDATE ttc_message_cache SET content = $1 WHERE message_id = $2"#,
+            r#"UPDATE ttc_message_cache SET content = $1, edited_at = $2 WHERE message_id = $3"#,
             new_content,
+            Utc::now(),
             event.id.0 as i64
         )
         .execute(pool)
@@ -318,15 +665,90 @@ pub async fn message_update(
         "Error updating message cache"
     );
 
+    // The author is not always part of the event, so fall back to the cached or fetched message
+    if let Some(user_id) = event
+        .author
+        .as_ref()
+        .map(|author| author.id)
+        .or(cached_user_id)
+        .or_else(|| new.as_ref().map(|new| new.author.id))
+    {
+        event_store::record_event(
+            data,
+            event.guild_id,
+            user_id,
+            EventType::MessageEdit,
+            json!({
+                "message_id": event.id.0.to_string(),
+                "channel_id": event.channel_id.0.to_string(),
+                "old_content": old_content,
+                "new_content": new_content,
+            }),
+        )
+        .await;
+    }
+
+    // The blacklist only keeps the edit out of the log channels, the cache and the event store are
+    // still updated
+    if unwrap_or_return!(
+        data.config.log_blacklist_channels(LogCategory::Messages).await,
+        "Error getting blacklisted log channels"
+    )
+    .contains(&(event.channel_id.0 as i64))
+    {
+        return;
+    }
+
+    // Show what changed with a word diff, or attach the whole message before and after the edit
+    // if the diff is too long for the embed
+    let mut before_after = None;
+    match &old_content {
+        Some(old_content) => {
+            let diff = diff::render_diff(&diff::word_diff(old_content, &new_content));
+            if diff.trim().is_empty() {
+                message_embed.description("Only whitespace was changed.");
+            } else if diff.chars().count() <= 4096 {
+                message_embed.description(diff);
+            } else {
+                message_embed.description(
+                    "The changes are too long to show here, the message before and after the edit is attached.",
+                );
+                before_after = Some(format!(
+                    "Before:\n{}\n\nAfter:\n{}\n",
+                    old_content, new_content
+                ));
+            }
+        }
+        None => {
+            let new_content = if new_content.is_empty() {
+                "None".to_string()
+            } else {
+                shorten(&new_content, 1021)
+            };
+            message_embed.field("Old", "Not available.", false);
+            message_embed.field("New", new_content, false);
+        }
+    }
+    let filename = format!("edit-{}.txt", event.id.0);
+
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::Messages).await,
+        "Error getting log channels"
     );
 
     for channel in &conv_channels {
         unwrap_or_return!(
             ChannelId(*channel as u64)
-                .send_message(ctx, |m| m.set_embed(message_embed.clone()))
+                .send_message(ctx, |m| {
+                    m.set_embed(message_embed.clone());
+                    if let Some(before_after) = &before_after {
+                        m.add_file(AttachmentType::Bytes {
+                            data: Cow::from(before_after.as_bytes()),
+                            filename: filename.clone(),
+                        });
+                    }
+                    m
+                })
                 .await,
             "Error sending message"
         );
@@ -334,9 +756,33 @@ pub async fn message_update(
 }
 
 pub async fn guild_member_addition(ctx: &Context, new_member: &Member, data: &Data) {
+    let used_invite = invites::member_joined(ctx, new_member, data).await;
+    let (invite_code, inviter) = match &used_invite {
+        Some(used_invite) => (
+            used_invite.code.clone(),
+            match &used_invite.inviter {
+                Some(inviter) => format!("<@{}> ({})", inviter.id, inviter.tag()),
+                None => "Unknown".to_string(),
+            },
+        ),
+        None => ("Unknown".to_string(), "Unknown".to_string()),
+    };
+
+    event_store::record_event(
+        data,
+        Some(new_member.guild_id),
+        new_member.user.id,
+        EventType::MemberJoin,
+        json!({ "account_created": new_member.user.created_at().unix_timestamp() }),
+    )
+    .await;
+
+    // Members can join with a copied name, not only change to one
+    check_impersonation(ctx, new_member, data).await;
+
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::MemberJoinLeave).await,
+        "Error getting log channels"
     );
     let color = data.colors.conveyance_member_join().await;
     for channel in &conv_channels {
@@ -353,6 +799,8 @@ pub async fn guild_member_addition(ctx: &Context, new_member: &Member, data: &Da
                                 &new_member.user.created_at().readable(),
                                 false,
                             )
+                            .field("Invited via", &invite_code, true)
+                            .field("Inviter", &inviter, true)
                             .timestamp(Utc::now())
                     })
                 })
@@ -364,10 +812,32 @@ pub async fn guild_member_addition(ctx: &Context, new_member: &Member, data: &Da
 
 pub async fn guild_member_removal(
     ctx: &Context,
+    guild_id: &GuildId,
     user: &User,
     member: &Option<Member>,
     data: &Data,
 ) {
+    event_store::record_event(
+        data,
+        Some(*guild_id),
+        user.id,
+        EventType::MemberLeave,
+        json!({
+            "joined_at": member
+                .as_ref()
+                .and_then(|member| member.joined_at)
+                .map(|joined_at| joined_at.unix_timestamp()),
+            "roles": member.as_ref().map(|member| {
+                member
+                    .roles
+                    .iter()
+                    .map(|role| role.0.to_string())
+                    .collect::<Vec<String>>()
+            }),
+        }),
+    )
+    .await;
+
     let joined_at = match member {
         Some(member) => match member.joined_at {
             Some(joined_at) => joined_at.readable(),
@@ -377,8 +847,8 @@ pub async fn guild_member_removal(
     };
 
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::MemberJoinLeave).await,
+        "Error getting log channels"
     );
     let color = data.colors.conveyance_member_leave().await;
     for channel in &conv_channels {
@@ -399,15 +869,30 @@ pub async fn guild_member_removal(
         );
     }
 }
-pub async fn guild_ban_addition(ctx: &Context, banned_user: &User, data: &Data) {
+pub async fn guild_ban_addition(
+    ctx: &Context,
+    guild_id: &GuildId,
+    banned_user: &User,
+    data: &Data,
+) {
+    let event_id = event_store::record_event(
+        data,
+        Some(*guild_id),
+        banned_user.id,
+        EventType::Ban,
+        json!({}),
+    )
+    .await;
+
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::Bans).await,
+        "Error getting log channels"
     );
 
     let color = data.colors.conveyance_ban_addition().await;
+    let mut sent = Vec::new();
     for channel in &conv_channels {
-        unwrap_or_return!(
+        sent.push(unwrap_or_return!(
             ChannelId(*channel as u64)
                 .send_message(ctx, |m| {
                     m.embed(|e| {
@@ -420,14 +905,44 @@ pub async fn guild_ban_addition(ctx: &Context, banned_user: &User, data: &Data)
                 })
                 .await,
             "Error sending message"
-        );
+        ));
+    }
+
+    if let Some(attribution) = audit_log::find_attribution(
+        ctx,
+        data,
+        *guild_id,
+        Action::Member(MemberAction::BanAdd),
+        banned_user.id.0,
+        None,
+    )
+    .await
+    {
+        audit_log::add_attribution(ctx, sent, &attribution).await;
+        if let Some(event_id) = event_id {
+            event_store::add_event_attribution(data, event_id, &attribution).await;
+        }
     }
 }
 
-pub async fn guild_ban_removal(ctx: &Context, unbanned_user: &User, data: &Data) {
+pub async fn guild_ban_removal(
+    ctx: &Context,
+    guild_id: &GuildId,
+    unbanned_user: &User,
+    data: &Data,
+) {
+    event_store::record_event(
+        data,
+        Some(*guild_id),
+        unbanned_user.id,
+        EventType::Unban,
+        json!({}),
+    )
+    .await;
+
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::Bans).await,
+        "Error getting log channels"
     );
     let color = data.colors.conveyance_unban().await;
     for channel in &conv_channels {
@@ -467,6 +982,12 @@ pub async fn guild_member_update(ctx: &Context, old: &Option<Member>, new: &Memb
     };
     let new_roles = new.roles.clone();
     let new_timeouted = is_user_timed_out(new);
+    // Role changes have their own kind of audit log entry
+    let action = if old_roles != new_roles {
+        MemberAction::RoleUpdate
+    } else {
+        MemberAction::Update
+    };
     // Make sure it is only the values displayed that have changed
     if !(old_nickname != new_nickname
         || old_roles != new_roles
@@ -481,6 +1002,28 @@ pub async fn guild_member_update(ctx: &Context, old: &Option<Member>, new: &Memb
         return;
     }
 
+    let role_ids = |roles: &Vec<RoleId>| {
+        roles
+            .iter()
+            .map(|role| role.0.to_string())
+            .collect::<Vec<String>>()
+    };
+    let event_id = event_store::record_event(
+        data,
+        Some(new.guild_id),
+        new.user.id,
+        EventType::MemberUpdate,
+        json!({
+            "old_nickname": old.as_ref().and_then(|old| old.nick.clone()),
+            "new_nickname": new.nick,
+            "old_roles": old.as_ref().map(|old| role_ids(&old.roles)),
+            "new_roles": role_ids(&new.roles),
+            "old_timed_out": old_timeouted,
+            "new_timed_out": new_timeouted,
+        }),
+    )
+    .await;
+
     let mut old_roles_string = String::new();
     let mut new_roles_string = String::new();
 
@@ -505,12 +1048,13 @@ pub async fn guild_member_update(ctx: &Context, old: &Option<Member>, new: &Memb
     }
 
     let conv_channels = unwrap_or_return!(
-        data.config.conveyance_channel().await,
-        "Error getting conveyance channels"
+        data.config.log_channels(LogCategory::MemberUpdates).await,
+        "Error getting log channels"
     );
     let color = data.colors.conveyance_member_update().await;
+    let mut sent = Vec::new();
     for channel in &conv_channels {
-        unwrap_or_return!(
+        sent.push(unwrap_or_return!(
             ChannelId(*channel as u64)
                 .send_message(ctx, |m| {
                     m.embed(|e| {
@@ -528,6 +1072,256 @@ pub async fn guild_member_update(ctx: &Context, old: &Option<Member>, new: &Memb
                 })
                 .await,
             "Error sending message"
+        ));
+    }
+
+    if let Some(attribution) = audit_log::find_attribution(
+        ctx,
+        data,
+        new.guild_id,
+        Action::Member(action),
+        new.user.id.0,
+        None,
+    )
+    .await
+    {
+        audit_log::add_attribution(ctx, sent, &attribution).await;
+        if let Some(event_id) = event_id {
+            event_store::add_event_attribution(data, event_id, &attribution).await;
+        }
+    }
+}
+
+// Send logging messages when the username, display name or avatar of a member changes. The
+// user update event is only sent for the bot itself, so the changes are taken from member updates.
+pub async fn member_profile_update(ctx: &Context, old: &Option<Member>, new: &Member, data: &Data) {
+    // Without the old member there is nothing to compare to
+    let old = match old {
+        Some(old) => old,
+        None => return,
+    };
+
+    let name_changed = old.user.tag() != new.user.tag();
+    let global_name_changed = old.user.global_name != new.user.global_name;
+    let avatar_changed = old.user.avatar != new.user.avatar;
+
+    if name_changed || global_name_changed || old.nick != new.nick {
+        check_impersonation(ctx, new, data).await;
+    }
+    if !(name_changed || global_name_changed || avatar_changed) {
+        return;
+    }
+
+    event_store::record_event(
+        data,
+        Some(new.guild_id),
+        new.user.id,
+        EventType::ProfileUpdate,
+        json!({
+            "old_name": old.user.tag(),
+            "new_name": new.user.tag(),
+            "old_global_name": old.user.global_name,
+            "new_global_name": new.user.global_name,
+            "old_avatar": old.user.avatar_url(),
+            "new_avatar": new.user.avatar_url(),
+        }),
+    )
+    .await;
+
+    let color = data.colors.conveyance_profile_update().await;
+    let mut profile_embed = CreateEmbed::default();
+    profile_embed
+        .title("Profile updated")
+        .color(color)
+        .field("User", new.user.tag(), true)
+        .field("UserID", new.user.id, true)
+        .timestamp(Utc::now());
+    if name_changed {
+        profile_embed.field("Old username", old.user.tag(), false);
+        profile_embed.field("New username", new.user.tag(), false);
+    }
+    if global_name_changed {
+        profile_embed.field(
+            "Old display name",
+            old.user.global_name.as_deref().unwrap_or("None"),
+            false,
+        );
+        profile_embed.field(
+            "New display name",
+            new.user.global_name.as_deref().unwrap_or("None"),
+            false,
+        );
+    }
+
+    // The old avatar is the thumbnail of the log and the new one gets an embed of its own
+    let mut embeds = Vec::new();
+    if avatar_changed {
+        profile_embed
+            .field(
+                "Avatar",
+                "Changed, the old avatar is shown here and the new one below",
+                false,
+            )
+            .thumbnail(old.user.face());
+        embeds.push(profile_embed);
+        let mut new_avatar_embed = CreateEmbed::default();
+        new_avatar_embed
+            .title("New avatar")
+            .color(color)
+            .thumbnail(new.user.face());
+        embeds.push(new_avatar_embed);
+    } else {
+        embeds.push(profile_embed);
+    }
+
+    let conv_channels = unwrap_or_return!(
+        data.config.log_channels(LogCategory::MemberUpdates).await,
+        "Error getting log channels"
+    );
+    for channel in &conv_channels {
+        unwrap_or_return!(
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| m.set_embeds(embeds.clone()))
+                .await,
+            "Error sending message"
+        );
+    }
+}
+
+// Alert the moderators if a name of a member is very similar to the name of a staff member
+async fn check_impersonation(ctx: &Context, member: &Member, data: &Data) {
+    let threshold = match unwrap_or_return!(
+        data.config.impersonation_alert_threshold().await,
+        "Error getting the impersonation alert threshold"
+    ) {
+        Some(threshold) => threshold,
+        None => return,
+    };
+    let staff_role = RoleId(unwrap_or_return!(
+        data.config.moderator_role().await,
+        "Error getting the moderator role"
+    ) as u64);
+
+    let mut names = vec![member.user.name.clone()];
+    names.extend(member.user.global_name.clone());
+    names.extend(member.nick.clone());
+
+    let impersonation = match impersonation::find_impersonation(
+        ctx,
+        member.guild_id,
+        staff_role,
+        member.user.id,
+        &names,
+        threshold,
+    ) {
+        Some(impersonation) => impersonation,
+        None => return,
+    };
+
+    let conv_channels = unwrap_or_return!(
+        data.config.log_channels(LogCategory::Automod).await,
+        "Error getting log channels"
+    );
+    let color = data.colors.impersonation_alert().await;
+    for channel in &conv_channels {
+        unwrap_or_return!(
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Possible impersonation")
+                            .description(format!(
+                                "<@{}> has a name very similar to staff member <@{}>.",
+                                member.user.id, impersonation.staff_id
+                            ))
+                            .color(color)
+                            .field("User", member.user.tag(), true)
+                            .field("UserID", member.user.id, true)
+                            .field("Name", &impersonation.name, false)
+                            .field("Similar to", &impersonation.staff_name, true)
+                            .field(
+                                "Similarity",
+                                format!("{}%", (impersonation.similarity * 100.0).round()),
+                                true,
+                            )
+                            .timestamp(Utc::now())
+                    })
+                })
+                .await,
+            "Error sending message"
+        );
+    }
+}
+
+// Send logging messages when members join, leave or move between voice channels
+pub async fn voice_state_update(
+    ctx: &Context,
+    old: &Option<VoiceState>,
+    new: &VoiceState,
+    data: &Data,
+) {
+    let old_channel = old.as_ref().and_then(|old| old.channel_id);
+    let new_channel = new.channel_id;
+
+    // Make sure neither of the channels is blacklisted from the voice logs
+    let blacklist = unwrap_or_return!(
+        data.config.log_blacklist_channels(LogCategory::Voice).await,
+        "Error getting blacklisted log channels"
+    );
+    if [old_channel, new_channel]
+        .iter()
+        .flatten()
+        .any(|channel| blacklist.contains(&(channel.0 as i64)))
+    {
+        return;
+    }
+
+    // Mutes, deafens and the like don't change the channel and aren't logged
+    let (title, color) = match (old_channel, new_channel) {
+        (None, Some(_)) => ("Joined voice channel", data.colors.conveyance_voice_join().await),
+        (Some(_), None) => ("Left voice channel", data.colors.conveyance_voice_leave().await),
+        (Some(old_channel), Some(new_channel)) if old_channel != new_channel => {
+            ("Moved voice channel", data.colors.conveyance_voice_move().await)
+        }
+        _ => return,
+    };
+
+    // Get the user from the event if possible, otherwise from either cache or rest api
+    let user = match &new.member {
+        Some(member) => member.user.clone(),
+        None => match new.user_id.to_user(ctx).await {
+            Ok(user) => user,
+            Err(why) => {
+                log::warn!("Error getting user based on user id: {}", why);
+                User::default()
+            }
+        },
+    };
+
+    let conv_channels = unwrap_or_return!(
+        data.config.log_channels(LogCategory::Voice).await,
+        "Error getting log channels"
+    );
+    for channel in &conv_channels {
+        unwrap_or_return!(
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title(title)
+                            .color(color)
+                            .field("User", user.tag(), true)
+                            .field("UserID", new.user_id, true)
+                            .timestamp(Utc::now());
+                        if let Some(old_channel) = old_channel {
+                            e.field("From", format!("<#{}>", old_channel), false);
+                        }
+                        if let Some(new_channel) = new_channel {
+                            e.field("To", format!("<#{}>", new_channel), false);
+                        }
+                        e
+                    })
+                })
+                .await,
+            "Error sending message"
         );
     }
 }
@@ -52,22 +52,17 @@ pub async fn message_delete(
         },
     };
     // If the deleted message was sent before the latest cache message
-    if msg.message_time.unwrap().timestamp() < cache.timestamp_unix {
+    if msg.message_time.timestamp() < cache.timestamp_unix {
         let mut emoji_cache = EmojiCache::new(&data.pool);
         let emojis = unwrap_or_return!(
             guild_id.unwrap().emojis(ctx).await,
             "can't get emojis from guild"
         );
         for emoji in emojis {
-            if msg
-                .content
-                .as_ref()
-                .unwrap()
-                .contains(&format!("<:{}:", emoji.name))
-            {
+            if msg.content.contains(&format!("<:{}:", emoji.name)) {
                 unwrap_or_return!(
                     emoji_cache
-                        .decrease_emoji_count(msg.user_id.unwrap() as u64, emoji.name, 1)
+                        .decrease_emoji_count(msg.user_id as u64, emoji.name, 1)
                         .await,
                     "error decreasing the emoji count"
                 );
@@ -75,7 +70,7 @@ pub async fn message_delete(
         }
         unwrap_or_return!(
             emoji_cache
-                .decrease_message_count(msg.user_id.unwrap() as u64, 1)
+                .decrease_message_count(msg.user_id as u64, 1)
                 .await,
             "error decreasing the message count"
         );
@@ -155,7 +150,7 @@ pub async fn message_update(
         for emoji in &emoji_list {
             let emoji_pattern = format!("<:{}:", emoji.name);
             let new_contains = new.content.contains(&emoji_pattern);
-            let old_contains = msg.content.as_ref().unwrap().contains(&emoji_pattern);
+            let old_contains = msg.content.contains(&emoji_pattern);
 
             if new_contains && !old_contains {
                 unwrap_or_return!(
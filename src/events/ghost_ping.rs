@@ -0,0 +1,249 @@
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use poise::serenity_prelude::{
+    ChannelId, Context, GuildId, MessageId, MessageUpdateEvent, RoleId, UserId,
+};
+
+use crate::{types::data::Data, unwrap_or_return};
+
+// How long after a notice no other notice is posted for the same author in the same channel
+const NOTICE_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// The mentions of a message that disappeared, leaving no trace of the ping
+pub struct GhostPing {
+    pub author: UserId,
+    users: Vec<i64>,
+    roles: Vec<i64>,
+}
+
+/// Get the mentions that are no longer in the message, leaving out the author mentioning themselves
+fn removed_mentions(old: &[i64], new: &[i64], author: i64) -> Vec<i64> {
+    old.iter()
+        .filter(|id| !new.contains(id) && **id != author)
+        .copied()
+        .collect()
+}
+
+/// Check whether a ghost ping notice should be posted for a message
+async fn should_notify(
+    ctx: &Context,
+    data: &Data,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    author: UserId,
+    age: i64,
+) -> Result<bool, crate::Error> {
+    let window = match data.config.ghost_ping_window().await? {
+        Some(window) => window,
+        None => return Ok(false),
+    };
+    if age > window || author == ctx.cache.current_user_id() {
+        return Ok(false);
+    }
+    if data
+        .config
+        .ghost_ping_disabled_channels()
+        .await?
+        .contains(&(channel_id.0 as i64))
+    {
+        return Ok(false);
+    }
+
+    // Members with an exempt role, like the moderators, can remove their pings freely
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(false),
+    };
+    let exempt_roles = data.config.ghost_ping_exempt_roles().await?;
+    if !exempt_roles.is_empty() {
+        if let Ok(member) = guild_id.member(ctx, author).await {
+            if member
+                .roles
+                .iter()
+                .any(|role| exempt_roles.contains(&(role.0 as i64)))
+            {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Post the notice in the channel the ping was removed from, unless one was posted there recently
+/// for the same author
+pub async fn send_notice(ctx: &Context, data: &Data, channel_id: ChannelId, ghost_ping: GhostPing) {
+    {
+        let mut notices = data.ghost_ping_notices.write().await;
+        notices.retain(|_, sent_at| sent_at.elapsed() < NOTICE_COOLDOWN);
+        if notices.contains_key(&(channel_id, ghost_ping.author)) {
+            return;
+        }
+        notices.insert((channel_id, ghost_ping.author), Instant::now());
+    }
+
+    let mut targets = ghost_ping
+        .users
+        .iter()
+        .map(|id| format!("<@{}>", id))
+        .collect::<Vec<String>>();
+    targets.extend(ghost_ping.roles.iter().map(|id| format!("<@&{}>", id)));
+    let mut targets = targets.join(", ");
+    if targets.len() > 1024 {
+        targets.truncate(1020);
+        if let Some(index) = targets.rfind(',') {
+            targets.truncate(index);
+        }
+        targets.push_str(" ...");
+    }
+
+    let color = data.colors.ghost_ping().await;
+    unwrap_or_return!(
+        channel_id
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title("Ghost ping")
+                        .description(format!(
+                            "<@{}> removed a message mentioning someone.",
+                            ghost_ping.author
+                        ))
+                        .field("Mentioned", targets, false)
+                        .color(color)
+                        .timestamp(Utc::now())
+                })
+                // The notice names the targets without pinging them again
+                .allowed_mentions(|a| a.empty_parse())
+            })
+            .await,
+        "Error sending ghost ping notice"
+    );
+}
+
+// Get the ghost ping left by a deleted message, if a notice should be posted for it. Whether the
+// author deleted the message themselves is only known after conveyance has checked the audit log,
+// so the notice is sent from there.
+pub async fn message_delete(
+    ctx: &Context,
+    guild_id: &Option<GuildId>,
+    channel_id: &ChannelId,
+    deleted_message_id: &MessageId,
+    data: &Data,
+) -> Option<GhostPing> {
+    let msg = match sqlx::query!(
+        r#"SELECT user_id, message_time, mentioned_users, mentioned_roles FROM ttc_message_cache WHERE message_id = $1"#,
+        deleted_message_id.0 as i64
+    )
+    .fetch_optional(&*data.pool)
+    .await
+    {
+        Ok(msg) => msg?,
+        Err(why) => {
+            log::error!("Error reading message from message cache database: {}", why);
+            return None;
+        }
+    };
+    let ghost_ping = GhostPing {
+        author: UserId(msg.user_id as u64),
+        users: removed_mentions(&msg.mentioned_users, &[], msg.user_id),
+        roles: msg.mentioned_roles,
+    };
+    if ghost_ping.users.is_empty() && ghost_ping.roles.is_empty() {
+        return None;
+    }
+
+    let age = (Utc::now() - msg.message_time).num_seconds();
+    match should_notify(ctx, data, *guild_id, *channel_id, ghost_ping.author, age).await {
+        Ok(true) => Some(ghost_ping),
+        Ok(false) => None,
+        Err(why) => {
+            log::error!("Error checking for a ghost ping: {}", why);
+            None
+        }
+    }
+}
+
+// Post a notice when an edit removes mentions from a recently sent message, the cached mentions
+// are updated to the ones after the edit
+pub async fn message_update(ctx: &Context, event: &MessageUpdateEvent, data: &Data) {
+    // The mentions are only part of the event when the content changed
+    let (new_users, new_roles) = match (&event.mentions, &event.mention_roles) {
+        (Some(users), Some(roles)) => (
+            users
+                .iter()
+                .map(|user| user.id.0 as i64)
+                .collect::<Vec<i64>>(),
+            roles
+                .iter()
+                .map(|role: &RoleId| role.0 as i64)
+                .collect::<Vec<i64>>(),
+        ),
+        _ => return,
+    };
+
+    let msg = match sqlx::query!(
+        r#"SELECT user_id, message_time, mentioned_users, mentioned_roles FROM ttc_message_cache WHERE message_id = $1"#,
+        event.id.0 as i64
+    )
+    .fetch_optional(&*data.pool)
+    .await
+    {
+        Ok(Some(msg)) => msg,
+        Ok(None) => return,
+        Err(why) => {
+            log::error!("Error reading message from message cache database: {}", why);
+            return;
+        }
+    };
+
+    unwrap_or_return!(
+        sqlx::query!(
+            r#"UPDATE ttc_message_cache SET mentioned_users = $1, mentioned_roles = $2 WHERE message_id = $3"#,
+            &new_users,
+            &new_roles,
+            event.id.0 as i64
+        )
+        .execute(&*data.pool)
+        .await,
+        "Error updating message cache"
+    );
+
+    let ghost_ping = GhostPing {
+        author: UserId(msg.user_id as u64),
+        users: removed_mentions(&msg.mentioned_users, &new_users, msg.user_id),
+        roles: removed_mentions(&msg.mentioned_roles, &new_roles, 0),
+    };
+    if ghost_ping.users.is_empty() && ghost_ping.roles.is_empty() {
+        return;
+    }
+
+    let age = (Utc::now() - msg.message_time).num_seconds();
+    if !unwrap_or_return!(
+        should_notify(
+            ctx,
+            data,
+            event.guild_id,
+            event.channel_id,
+            ghost_ping.author,
+            age
+        )
+        .await,
+        "Error checking for a ghost ping"
+    ) {
+        return;
+    }
+
+    send_notice(ctx, data, event.channel_id, ghost_ping).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_ping_mentions() {
+        assert_eq!(removed_mentions(&[1, 2, 3], &[2], 3), vec![1]);
+        assert_eq!(removed_mentions(&[1, 2], &[1, 2], 3), Vec::<i64>::new());
+        assert_eq!(removed_mentions(&[], &[1], 3), Vec::<i64>::new());
+    }
+}
@@ -42,8 +42,50 @@ pub async fn interaction_create(ctx: &Context, intr: &Interaction, data: &Data)
                                 }
                             }
                         }
-                        // Self role menu interaction
-                        "ttc-bot-self-role-menu" => {
+                        // The button to open the captcha modal
+                        "ttc-bot-captcha-button" => {
+                            match interaction_fns::captcha_button(ctx, intr, data).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!("Error completing captcha interaction: {}", why);
+                                }
+                            }
+                        }
+                        // The buttons to flip through the onboarding rules
+                        custom_id if custom_id.starts_with("ttc-bot-onboarding-page-") => {
+                            match interaction_fns::onboarding_page_button(ctx, intr, data).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!("Error completing onboarding interaction: {}", why);
+                                }
+                            }
+                        }
+                        // The button to agree to the rules at the end of the onboarding
+                        "ttc-bot-onboarding-agree" => {
+                            match interaction_fns::onboarding_agree_button(ctx, intr, data).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!("Error completing onboarding interaction: {}", why);
+                                }
+                            }
+                        }
+                        // The approve and deny buttons of manual verification requests
+                        custom_id
+                            if custom_id.starts_with("ttc-bot-verification-approve-")
+                                || custom_id.starts_with("ttc-bot-verification-deny-") =>
+                        {
+                            match interaction_fns::verification_request(ctx, intr, data).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!(
+                                        "Error completing verification request interaction: {}",
+                                        why
+                                    );
+                                }
+                            }
+                        }
+                        // Self role menu interaction, the group menus carry the group id
+                        custom_id if custom_id.starts_with("ttc-bot-self-role-menu") => {
                             match interaction_fns::self_role_menu(ctx, intr, data).await {
                                 Ok(_) => (),
                                 Err(why) => {
@@ -54,6 +96,18 @@ pub async fn interaction_create(ctx: &Context, intr: &Interaction, data: &Data)
                                 }
                             }
                         }
+                        // The claim button on the support ticket summaries
+                        "ttc-bot-ticket-claim" => {
+                            match interaction_fns::ticket_claim_button(ctx, intr, data).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!(
+                                        "Error completing ticket claim interaction: {}",
+                                        why
+                                    );
+                                }
+                            }
+                        }
                         "ttc-bot-ticket-button" => {
                             match interaction_fns::ticket_button(ctx, &intr).await {
                                 Ok(_) => (),
@@ -95,148 +149,1178 @@ pub async fn interaction_create(ctx: &Context, intr: &Interaction, data: &Data)
                 None => return,
             };
 
-            if let "ttc-bot-ticket-modal" = &intr.data.custom_id[..] {
-                match interaction_fns::ticket_modal(ctx, &intr, data).await {
-                    Ok(_) => (),
-                    Err(why) => {
+            match &intr.data.custom_id[..] {
+                "ttc-bot-ticket-modal" => {
+                    match interaction_fns::ticket_modal(ctx, &intr, data).await {
+                        Ok(_) => (),
+                        Err(why) => {
+                            let color = data.colors.input_error().await;
+                            match intr
+                                .edit_original_interaction_response(ctx, |m| {
+                                    m.embed(|e| {
+                                        e.title("An error occurred")
+                                            .description(format!("{}", why))
+                                            .color(color)
+                                    })
+                                })
+                                .await
+                            {
+                                Ok(_) => (),
+                                Err(why) => log::error!("Failed to send error message: {}", why),
+                            }
+                            log::warn!("Failed to complete support ticket creation: {}", why);
+                        }
+                    }
+                }
+                "ttc-bot-captcha-modal" => {
+                    match interaction_fns::captcha_modal(ctx, &intr, data).await {
+                        Ok(_) => (),
+                        Err(why) => {
+                            let color = data.colors.input_error().await;
+                            match intr
+                                .edit_original_interaction_response(ctx, |m| {
+                                    m.embed(|e| {
+                                        e.title("Verification failed")
+                                            .description(format!("{}", why))
+                                            .color(color)
+                                    })
+                                })
+                                .await
+                            {
+                                Ok(_) => (),
+                                Err(why) => log::error!("Failed to send error message: {}", why),
+                            }
+                            log::warn!("Failed to complete captcha: {}", why);
+                        }
+                    }
+                }
+                "ttc-bot-onboarding-modal" => {
+                    match interaction_fns::onboarding_modal(ctx, &intr, data).await {
+                        Ok(_) => (),
+                        Err(why) => {
+                            let color = data.colors.input_error().await;
+                            match intr
+                                .edit_original_interaction_response(ctx, |m| {
+                                    m.embed(|e| {
+                                        e.title("Verification failed")
+                                            .description(format!("{}", why))
+                                            .color(color)
+                                    })
+                                    .components(|c| c)
+                                })
+                                .await
+                            {
+                                Ok(_) => (),
+                                Err(why) => log::error!("Failed to send error message: {}", why),
+                            }
+                            log::warn!("Failed to complete onboarding: {}", why);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        _ => (),
+    }
+}
+
+// Module for the separate interaction functions, to keep the main interaction functions clean
+mod interaction_fns {
+    use chrono::Utc;
+    use poise::serenity_prelude::{
+        ActionRowComponent, ButtonStyle, ChannelId, Color, Context, CreateComponents, CreateEmbed,
+        CreateInteractionResponseFollowup, GuildId, InputTextStyle, InteractionResponseFlags,
+        InteractionResponseType, Member, Mentionable, MessageComponentInteraction,
+        ModalSubmitInteraction, RoleId, Timestamp, User, UserId,
+    };
+    use rand::prelude::SliceRandom;
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{
+        command_error,
+        commands::support::{self, SupportThread},
+        traits::readable::Readable,
+        types::{data::Data, log_category::LogCategory},
+        utils::{
+            captcha::{self, PendingCaptcha},
+            welcome::{self, WelcomeInfo},
+        },
+        Error,
+    };
+
+    // Interaction for the verification button
+    pub async fn verification_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        // Defer the reply to avoid possible issues
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|d| d.flags(InteractionResponseFlags::EPHEMERAL))
+        })
+        .await?;
+
+        let mut member = intr.member.clone().unwrap();
+        let verified_role = RoleId(data.config.verified_role().await? as u64);
+
+        // Check if the user already has the verified role
+        if !member.roles.contains(&verified_role) {
+            // Make sure the account is older than the configured minimum age
+            let min_account_age = data.config.verification_min_account_age().await?;
+            let allowed_at = member.user.created_at().unix_timestamp() + min_account_age;
+            // Accounts approved by a moderator go through the rest of the verification as usual
+            let approved = sqlx::query!(
+                r#"SELECT user_id FROM ttc_verification_requests WHERE user_id = $1 AND approved_at IS NOT NULL"#,
+                intr.user.id.0 as i64
+            )
+            .fetch_optional(&*data.pool)
+            .await?
+            .is_some();
+            if Utc::now().timestamp() < allowed_at && !approved {
+                if data.config.verification_manual_approval().await? {
+                    return request_manual_approval(ctx, &intr, min_account_age, data).await;
+                }
+
+                let color = data.colors.general_error().await;
+                intr.edit_original_interaction_response(ctx, |i| {
+                    i.embed(|e| {
+                        e.title("Account too new")
+                            .description(format!(
+                                "Your account has to be at least {} old to be verified on this server. You can try again <t:{}:F> (<t:{}:R>).",
+                                chrono::Duration::seconds(min_account_age).readable(),
+                                allowed_at,
+                                allowed_at
+                            ))
+                            .color(color)
+                    })
+                })
+                .await?;
+
+                log_verification_rejection(ctx, &member.user, allowed_at, data).await?;
+                return Ok(());
+            }
+
+            // Let the user prove they are human before giving out the role
+            if data.config.captcha_enabled().await? {
+                // Everything slow happens outside of the lock, it is shared by all verifying users
+                let timeout = data.config.captcha_timeout().await?;
+                let max_attempts = data.config.captcha_attempts().await?;
+                let mut captchas = data.captchas.write().await;
+                captchas.retain(|_, captcha| !captcha.is_expired());
+
+                // Restarting the verification gives a new captcha, but not new attempts. A user
+                // that used up their attempts has to wait until the captcha expires.
+                let captcha = match captchas.remove(&intr.user.id) {
+                    Some(captcha) if captcha.attempts_left <= 0 => {
+                        let expires_at = captcha.expires_at.unix_timestamp();
+                        captchas.insert(intr.user.id, captcha);
+                        drop(captchas);
+
                         let color = data.colors.input_error().await;
-                        match intr
-                            .edit_original_interaction_response(ctx, |m| {
-                                m.embed(|e| {
-                                    e.title("An error occurred")
-                                        .description(format!("{}", why))
-                                        .color(color)
+                        intr.edit_original_interaction_response(ctx, |i| {
+                            i.embed(|e| {
+                                e.title("Out of captcha attempts")
+                                    .description(format!("You can try again <t:{}:R>.", expires_at))
+                                    .color(color)
+                            })
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                    Some(mut captcha) => {
+                        captcha.answer = captcha::generate_captcha_text();
+                        captcha
+                    }
+                    None => {
+                        let expires_at: Timestamp =
+                            (Utc::now() + chrono::Duration::seconds(timeout)).into();
+                        PendingCaptcha::new(
+                            captcha::generate_captcha_text(),
+                            max_attempts,
+                            expires_at,
+                        )
+                    }
+                };
+                let attempts = captcha.attempts_left;
+                let expires_at = captcha.expires_at;
+                let answer = captcha.answer.clone();
+                captchas.insert(intr.user.id, captcha);
+                drop(captchas);
+                let path = captcha::render_captcha(&answer, intr.user.id)?;
+
+                let color = data.colors.captcha().await;
+                intr.edit_original_interaction_response(ctx, |i| {
+                    i.embed(|e| {
+                        e.title("Captcha required")
+                            .description(
+                                "Please solve the captcha below to finish the verification.",
+                            )
+                            .color(color)
+                    })
+                })
+                .await?;
+                let result = intr
+                    .create_followup_message(ctx, |f| {
+                        captcha_followup(
+                            f,
+                            path.as_str(),
+                            intr.user.id,
+                            attempts,
+                            expires_at,
+                            color,
+                        )
+                    })
+                    .await;
+                captcha::remove_captcha(&path);
+                result?;
+                return Ok(());
+            }
+
+            // Walk the user through the rules and questions before giving out the role
+            if let Some((embed, components)) = start_onboarding(intr.user.id, data).await? {
+                intr.edit_original_interaction_response(ctx, |i| {
+                    i.set_embed(embed).components(|c| {
+                        *c = components;
+                        c
+                    })
+                })
+                .await?;
+                return Ok(());
+            }
+
+            let embed = finish_verification(ctx, &mut member, data).await?;
+            // Send a message to the user to acknowledge the verification
+            match intr
+                .edit_original_interaction_response(ctx, |i| i.set_embed(embed))
+                .await
+            {
+                Ok(_) => {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    send_welcome_message(ctx, intr.guild_id.unwrap(), &intr.user, data).await?;
+                }
+                Err(why) => {
+                    log::error!("Unable to respond to interaction: {}", why);
+                }
+            }
+        } else {
+            let color = data.colors.general_error().await;
+            // If the user has already verified tell them about it
+            intr.edit_original_interaction_response(ctx, |i| {
+                i.embed(|e: &mut CreateEmbed| {
+                    e.title("Verification failed")
+                        .description("You are already verified! You can't over-verify yourself.")
+                        .color(color)
+                })
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    // Interaction for the button to open the captcha modal
+    pub async fn captcha_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        if !data.captchas.read().await.contains_key(&intr.user.id) {
+            let color = data.colors.input_error().await;
+            intr.create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.embed(|e| {
+                            e.title("No captcha pending")
+                                .description("Please start the verification again.")
+                                .color(color)
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id("ttc-bot-captcha-modal")
+                        .title("Captcha")
+                        .components(|c| {
+                            c.create_action_row(|a| {
+                                a.create_input_text(|t| {
+                                    t.label("The text shown in the image")
+                                        .max_length(32)
+                                        .custom_id("ttc-bot-captcha-modal-answer")
+                                        .required(true)
+                                        .style(InputTextStyle::Short)
                                 })
                             })
-                            .await
-                        {
-                            Ok(_) => (),
-                            Err(why) => log::error!("Failed to send error message: {}", why),
+                        })
+                })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Interaction for the captcha modal, checking the answer
+    pub async fn captcha_modal(
+        ctx: &Context,
+        intr: &ModalSubmitInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|d| d.flags(InteractionResponseFlags::EPHEMERAL))
+        })
+        .await?;
+
+        let mut answer = String::new();
+        for row in intr.data.components.iter() {
+            if let ActionRowComponent::InputText(input) = &row.components[0] {
+                if input.custom_id == "ttc-bot-captcha-modal-answer" {
+                    answer = input.value.clone();
+                }
+            }
+        }
+
+        let mut captchas = data.captchas.write().await;
+        let mut captcha = match captchas.remove(&intr.user.id) {
+            Some(captcha) => captcha,
+            None => {
+                return Err(Error::from(
+                    "There is no captcha pending, please start the verification again.",
+                ))
+            }
+        };
+
+        if captcha.is_expired() {
+            drop(captchas);
+            log_captcha_failure(ctx, &intr.user, "Captcha timed out", data).await?;
+            return Err(Error::from(
+                "The captcha has timed out, please start the verification again.",
+            ));
+        }
+
+        if captcha.attempts_left <= 0 {
+            let expires_at = captcha.expires_at.unix_timestamp();
+            captchas.insert(intr.user.id, captcha);
+            return Err(Error::from(format!(
+                "You have no captcha attempts left, you can try again <t:{}:R>.",
+                expires_at
+            )));
+        }
+
+        if !captcha.matches(&answer) {
+            captcha.attempts_left -= 1;
+            if captcha.attempts_left <= 0 {
+                // Keep the used up captcha until it expires, so restarting doesn't reset the attempts
+                let expires_at = captcha.expires_at.unix_timestamp();
+                captchas.insert(intr.user.id, captcha);
+                drop(captchas);
+                log_captcha_failure(ctx, &intr.user, "Out of captcha attempts", data).await?;
+                return Err(Error::from(format!(
+                    "Wrong answer and no attempts left, you can try again <t:{}:R>.",
+                    expires_at
+                )));
+            }
+
+            // Give the user a fresh captcha for the next attempt
+            let attempts = captcha.attempts_left;
+            let expires_at = captcha.expires_at;
+            captcha.answer = captcha::generate_captcha_text();
+            let answer = captcha.answer.clone();
+            captchas.insert(intr.user.id, captcha);
+            drop(captchas);
+            let path = captcha::render_captcha(&answer, intr.user.id)?;
+
+            let color = data.colors.input_error().await;
+            intr.edit_original_interaction_response(ctx, |i| {
+                i.embed(|e| {
+                    e.title("Wrong answer")
+                        .description("That was not quite right, here is a new captcha.")
+                        .color(color)
+                })
+            })
+            .await?;
+            let color = data.colors.captcha().await;
+            let result = intr
+                .create_followup_message(ctx, |f| {
+                    captcha_followup(f, path.as_str(), intr.user.id, attempts, expires_at, color)
+                })
+                .await;
+            captcha::remove_captcha(&path);
+            result?;
+            return Ok(());
+        }
+        drop(captchas);
+
+        if let Some((embed, components)) = start_onboarding(intr.user.id, data).await? {
+            intr.edit_original_interaction_response(ctx, |i| {
+                i.set_embed(embed).components(|c| {
+                    *c = components;
+                    c
+                })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        let mut member = intr.member.clone().unwrap();
+        let embed = finish_verification(ctx, &mut member, data).await?;
+        intr.edit_original_interaction_response(ctx, |i| i.set_embed(embed))
+            .await?;
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        send_welcome_message(ctx, intr.guild_id.unwrap(), &intr.user, data).await?;
+
+        Ok(())
+    }
+
+    // Put the user into the onboarding if there are rules or questions configured, returns the
+    // first page to show them
+    async fn start_onboarding(
+        user_id: UserId,
+        data: &Data,
+    ) -> Result<Option<(CreateEmbed, CreateComponents)>, Error> {
+        let page = onboarding_page(0, data).await?;
+        if page.is_some() {
+            data.onboarding_users.write().await.insert(user_id);
+        }
+        Ok(page)
+    }
+
+    // Build a page of the onboarding, the rules come first and the agree button is on the last
+    // one. Returns `None` if there is nothing to go through.
+    async fn onboarding_page(
+        page: usize,
+        data: &Data,
+    ) -> Result<Option<(CreateEmbed, CreateComponents)>, Error> {
+        let rules = data.config.onboarding_rules().await?;
+        if rules.is_empty() && data.config.onboarding_questions().await?.is_empty() {
+            return Ok(None);
+        }
+
+        let color = data.colors.onboarding_rules().await;
+        let mut embed = CreateEmbed::default();
+        let mut components = CreateComponents::default();
+        // The rules may have been changed while the user was reading them
+        let page = page.min(rules.len().saturating_sub(1));
+        match rules.get(page) {
+            Some((title, content)) => {
+                embed
+                    .title(title)
+                    .description(content)
+                    .footer(|f| f.text(format!("Page {} of {}", page + 1, rules.len())))
+                    .color(color);
+                components.create_action_row(|a| {
+                    if page > 0 {
+                        a.create_button(|b| {
+                            b.label("Back")
+                                .custom_id(format!("ttc-bot-onboarding-page-{}", page - 1))
+                                .style(ButtonStyle::Secondary)
+                        });
+                    }
+                    if page + 1 < rules.len() {
+                        a.create_button(|b| {
+                            b.label("Next")
+                                .custom_id(format!("ttc-bot-onboarding-page-{}", page + 1))
+                                .style(ButtonStyle::Primary)
+                        })
+                    } else {
+                        a.create_button(|b| {
+                            b.label("I agree")
+                                .custom_id("ttc-bot-onboarding-agree")
+                                .style(ButtonStyle::Success)
+                        })
+                    }
+                });
+            }
+            None => {
+                // Without any rules only the questions are left
+                embed
+                    .title("A few questions")
+                    .description(
+                        "Please answer a few questions before getting access to the server.",
+                    )
+                    .color(color);
+                components.create_action_row(|a| {
+                    a.create_button(|b| {
+                        b.label("Answer the questions")
+                            .custom_id("ttc-bot-onboarding-agree")
+                            .style(ButtonStyle::Primary)
+                    })
+                });
+            }
+        }
+
+        Ok(Some((embed, components)))
+    }
+
+    // Tell a user that isn't in the onboarding to start the verification again
+    async fn onboarding_not_started(
+        ctx: &Context,
+        intr: &MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.input_error().await;
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| {
+                        e.title("No verification in progress")
+                            .description("Please start the verification again.")
+                            .color(color)
+                    })
+                    .ephemeral(true)
+                })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Interaction for the buttons to flip through the onboarding rules
+    pub async fn onboarding_page_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        if !data.onboarding_users.read().await.contains(&intr.user.id) {
+            return onboarding_not_started(ctx, &intr, data).await;
+        }
+
+        let page = intr
+            .data
+            .custom_id
+            .trim_start_matches("ttc-bot-onboarding-page-")
+            .parse::<usize>()?;
+        let (embed, components) = match onboarding_page(page, data).await? {
+            Some(page) => page,
+            None => return onboarding_not_started(ctx, &intr, data).await,
+        };
+
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).set_components(components))
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Interaction for agreeing to the rules, opens the questions if there are any
+    pub async fn onboarding_agree_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        if !data.onboarding_users.read().await.contains(&intr.user.id) {
+            return onboarding_not_started(ctx, &intr, data).await;
+        }
+
+        let questions = data.config.onboarding_questions().await?;
+        if questions.is_empty() {
+            intr.create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await?;
+            data.onboarding_users.write().await.remove(&intr.user.id);
+
+            let mut member = intr.member.clone().unwrap();
+            let embed = finish_verification(ctx, &mut member, data).await?;
+            intr.edit_original_interaction_response(ctx, |i| i.set_embed(embed).components(|c| c))
+                .await?;
+
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            send_welcome_message(ctx, intr.guild_id.unwrap(), &intr.user, data).await?;
+            return Ok(());
+        }
+
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id("ttc-bot-onboarding-modal")
+                        .title("A few questions")
+                        .components(|c| {
+                            for (index, (question, placeholder, required)) in
+                                questions.iter().enumerate()
+                            {
+                                c.create_action_row(|a| {
+                                    a.create_input_text(|t| {
+                                        t.label(question)
+                                            .max_length(1024)
+                                            .custom_id(format!(
+                                                "ttc-bot-onboarding-question-{}",
+                                                index
+                                            ))
+                                            .required(*required)
+                                            .style(InputTextStyle::Paragraph);
+                                        if let Some(placeholder) = placeholder {
+                                            t.placeholder(placeholder);
+                                        }
+                                        t
+                                    })
+                                });
+                            }
+                            c
+                        })
+                })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Interaction for the onboarding questions modal, the last step of the verification
+    pub async fn onboarding_modal(
+        ctx: &Context,
+        intr: &ModalSubmitInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::DeferredUpdateMessage)
+        })
+        .await?;
+
+        if !data.onboarding_users.write().await.remove(&intr.user.id) {
+            return Err(Error::from(
+                "There is no verification in progress, please start the verification again.",
+            ));
+        }
+
+        // Match the answers back to the questions they were given for
+        let questions = data.config.onboarding_questions().await?;
+        let mut answers: Vec<(String, String)> = Vec::new();
+        for row in intr.data.components.iter() {
+            if let ActionRowComponent::InputText(input) = &row.components[0] {
+                let question = input
+                    .custom_id
+                    .strip_prefix("ttc-bot-onboarding-question-")
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .and_then(|i| questions.get(i));
+                match question {
+                    Some((question, _, _)) => {
+                        if !input.value.trim().is_empty() {
+                            answers.push((question.clone(), input.value.clone()));
                         }
-                        log::warn!("Failed to complete support ticket creation: {}", why);
                     }
+                    None => log::warn!(
+                        "Invalid custom id for onboarding modal component: {}",
+                        input.custom_id
+                    ),
                 }
             }
         }
-        _ => (),
-    }
-}
 
-// Module for the separate interaction functions, to keep the main interaction functions clean
-mod interaction_fns {
-    use chrono::Utc;
-    use poise::serenity_prelude::{
-        ActionRowComponent, ChannelId, Context, CreateEmbed, InputTextStyle,
-        InteractionResponseFlags, InteractionResponseType, Mentionable,
-        MessageComponentInteraction, ModalSubmitInteraction, RoleId,
-    };
-    use rand::prelude::SliceRandom;
-    use std::time::Duration;
+        let pool = &*data.pool;
+        let answer_time = Utc::now();
+        for (question, answer) in &answers {
+            if let Err(why) = sqlx::query!(
+                r#"INSERT INTO ttc_onboarding_answers (user_id, question, answer, answer_time) VALUES($1, $2, $3, $4)"#,
+                intr.user.id.0 as i64,
+                question,
+                answer,
+                answer_time,
+            )
+            .execute(pool)
+            .await
+            {
+                return command_error!("Error writing into database: {}", why);
+            }
+        }
+
+        // The user shouldn't be stuck in the verification because the moderators can't be reached
+        if let Err(why) = post_onboarding_answers(ctx, &intr.user, &answers, data).await {
+            log::error!("Unable to post onboarding answers: {}", why);
+        }
 
-    use crate::{command_error, commands::support::SupportThread, types::data::Data, Error};
+        let mut member = intr.member.clone().unwrap();
+        let embed = finish_verification(ctx, &mut member, data).await?;
+        intr.edit_original_interaction_response(ctx, |i| i.set_embed(embed).components(|c| c))
+            .await?;
 
-    // Interaction for the verification button
-    pub async fn verification_button(
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        send_welcome_message(ctx, intr.guild_id.unwrap(), &intr.user, data).await?;
+
+        Ok(())
+    }
+
+    // Post the answers to the onboarding questions for the moderators to review
+    async fn post_onboarding_answers(
         ctx: &Context,
-        intr: MessageComponentInteraction,
+        user: &User,
+        answers: &[(String, String)],
         data: &Data,
     ) -> Result<(), Error> {
-        // Defer the reply to avoid possible issues
-        intr.create_interaction_response(ctx, |i| {
-            i.kind(InteractionResponseType::DeferredChannelMessageWithSource)
-                .interaction_response_data(|d| d.flags(InteractionResponseFlags::EPHEMERAL))
-        })
-        .await?;
+        // Fall back to the automod logs if there is no dedicated review channel
+        let channels = match data.config.onboarding_review_channel().await? {
+            Some(channel) => vec![channel],
+            None => data.config.log_channels(LogCategory::Automod).await?,
+        };
+
+        let color = data.colors.conveyance_onboarding_answers().await;
+        for channel in &channels {
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Onboarding completed")
+                            .field("User", user.tag(), true)
+                            .field("UserID", user.id, true)
+                            .thumbnail(user.face())
+                            .color(color)
+                            .timestamp(Utc::now());
+                        for (question, answer) in answers {
+                            e.field(question, answer, false);
+                        }
+                        e
+                    })
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
 
-        // Make sure accounts that enter are older than 7 days
-        if Utc::now().timestamp()
-            - intr
-                .member
-                .clone()
-                .unwrap()
-                .user
-                .created_at()
-                .unix_timestamp()
-            < chrono::Duration::days(7).num_seconds()
+    // Give out the verified role once every step of the verification is done, returns the embed
+    // to acknowledge it with
+    async fn finish_verification(
+        ctx: &Context,
+        member: &mut Member,
+        data: &Data,
+    ) -> Result<CreateEmbed, Error> {
+        if let Err(why) = member
+            .add_role(ctx, RoleId(data.config.verified_role().await? as u64))
+            .await
         {
-            let color = data.colors.general_error().await;
-            intr.edit_original_interaction_response(ctx, |i| {
-                i.embed(|e| {
-                    e.title("An error occurred")
-                        .description("Something went wrong.")
-                        .color(color)
+            return command_error!("Unable to add verified role: {}", why);
+        }
+
+        // A moderator approval is only needed until the verification is done
+        sqlx::query!(
+            r#"DELETE FROM ttc_verification_requests WHERE user_id = $1"#,
+            member.user.id.0 as i64
+        )
+        .execute(&*data.pool)
+        .await?;
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .title("Verified!")
+            .description("Successfully verified, enjoy your stay!")
+            .color(data.colors.verify_color().await);
+        Ok(embed)
+    }
+
+    // Build the message containing the captcha image and the button to answer it
+    fn captcha_followup<'a, 'b>(
+        f: &'b mut CreateInteractionResponseFollowup<'a>,
+        path: &'a str,
+        user_id: UserId,
+        attempts: i32,
+        expires_at: Timestamp,
+        color: Color,
+    ) -> &'b mut CreateInteractionResponseFollowup<'a> {
+        f.add_file(path)
+            .embed(|e| {
+                e.title("Captcha")
+                    .description(format!(
+                        "Type the text shown in the image. Attempts left: {}, expires <t:{}:R>.",
+                        attempts,
+                        expires_at.unix_timestamp()
+                    ))
+                    .attachment(captcha::get_captcha_filename(user_id))
+                    .color(color)
+            })
+            .components(|c| {
+                c.create_action_row(|a| {
+                    a.create_button(|b| {
+                        b.label("Enter the captcha")
+                            .custom_id("ttc-bot-captcha-button")
+                            .style(ButtonStyle::Primary)
+                    })
                 })
             })
-            .await?;
-            return Ok(());
+            .ephemeral(true)
+    }
+
+    // Log a failed captcha to the automod logs
+    async fn log_captcha_failure(
+        ctx: &Context,
+        user: &User,
+        reason: &str,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.conveyance_captcha_failed().await;
+        for channel in &data.config.log_channels(LogCategory::Automod).await? {
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Captcha failed")
+                            .description(reason)
+                            .field("User", user.tag(), true)
+                            .field("UserID", user.id, true)
+                            .field("Account created", user.created_at().readable(), false)
+                            .color(color)
+                            .timestamp(Utc::now())
+                    })
+                })
+                .await?;
         }
 
-        let color = data.colors.verify_color().await;
-        // Check if the user already has the verified role
+        Ok(())
+    }
+
+    // Interaction for the approve and deny buttons of a manual verification request
+    pub async fn verification_request(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        // Only moderators are allowed to decide on verification requests
+        let moderator_role = RoleId(data.config.moderator_role().await? as u64);
         if !intr
             .member
-            .clone()
+            .as_ref()
             .unwrap()
             .roles
-            .contains(&RoleId(data.config.verified_role().await? as u64))
+            .contains(&moderator_role)
         {
-            match intr
-                .member
-                .clone()
-                .unwrap()
-                .add_role(ctx, &RoleId(data.config.verified_role().await? as u64))
+            let color = data.colors.input_error().await;
+            intr.create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.embed(|e| {
+                            e.title("Missing permissions")
+                                .description("Only moderators can decide on verification requests.")
+                                .color(color)
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        let (approved, user_id) = match intr
+            .data
+            .custom_id
+            .strip_prefix("ttc-bot-verification-approve-")
+        {
+            Some(user_id) => (true, user_id),
+            None => (
+                false,
+                intr.data
+                    .custom_id
+                    .trim_start_matches("ttc-bot-verification-deny-"),
+            ),
+        };
+        let user_id = UserId(user_id.parse::<u64>()?);
+
+        let mut embed = match intr.message.embeds.first() {
+            Some(embed) => CreateEmbed::from(embed.clone()),
+            None => CreateEmbed::default(),
+        };
+
+        // The request is posted to several channels and may have been decided on in another one
+        // already. Approving only lets the account past the age check, the user still has to
+        // finish the rest of the verification.
+        let pending = if approved {
+            sqlx::query!(
+                r#"UPDATE ttc_verification_requests SET approved_at = $1 WHERE user_id = $2 AND approved_at IS NULL AND denied_at IS NULL"#,
+                Utc::now(),
+                user_id.0 as i64
+            )
+            .execute(&*data.pool)
+            .await?
+        } else {
+            // Denied requests are kept so the user can't ask the moderators again right away
+            sqlx::query!(
+                r#"UPDATE ttc_verification_requests SET denied_at = $1 WHERE user_id = $2 AND approved_at IS NULL AND denied_at IS NULL"#,
+                Utc::now(),
+                user_id.0 as i64
+            )
+            .execute(&*data.pool)
+            .await?
+        }
+        .rows_affected()
+            > 0;
+        if !pending {
+            embed.field("Result", "This request has already been handled.", false);
+            intr.create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+            })
+            .await?;
+            return Ok(());
+        }
+
+        let (decision, field) = if approved {
+            (
+                "Your verification request has been approved by the moderators, press the verification button again to finish the verification.",
+                "Approved by",
+            )
+        } else {
+            (
+                "Your verification request has been denied by the moderators, you can verify once your account is old enough.",
+                "Denied by",
+            )
+        };
+
+        // The user may have left or closed their DMs, which is not worth failing over
+        let dm_result = match user_id.create_dm_channel(ctx).await {
+            Ok(channel) => channel
+                .send_message(ctx, |m| m.content(decision))
                 .await
-            {
-                Ok(_) => {
-                    // Send a message to the user to acknowledge the verification
-                    match intr
-                        .edit_original_interaction_response(ctx, |i| {
-                            i.embed(|e: &mut CreateEmbed| {
-                                e.title("Verified!")
-                                    .description("Successfully verified, enjoy your stay!")
-                                    .color(color)
+                .map(|_| ()),
+            Err(why) => Err(why),
+        };
+        if let Err(why) = dm_result {
+            log::warn!(
+                "Unable to inform user about the verification decision: {}",
+                why
+            );
+        }
+        embed.field(field, intr.user.tag(), false);
+
+        // Replace the buttons with the decision so it can't be made twice
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Post the request of a too new account to the moderators
+    async fn request_manual_approval(
+        ctx: &Context,
+        intr: &MessageComponentInteraction,
+        min_account_age: i64,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.verification_pending().await;
+
+        // Make sure the moderators are not asked multiple times for the same user
+        let inserted = sqlx::query!(
+            r#"INSERT INTO ttc_verification_requests (user_id, requested_at) VALUES($1, $2) ON CONFLICT (user_id) DO NOTHING"#,
+            intr.user.id.0 as i64,
+            Utc::now()
+        )
+        .execute(&*data.pool)
+        .await?
+        .rows_affected()
+            > 0;
+        if !inserted {
+            let denied = sqlx::query!(
+                r#"SELECT denied_at FROM ttc_verification_requests WHERE user_id = $1"#,
+                intr.user.id.0 as i64
+            )
+            .fetch_optional(&*data.pool)
+            .await?
+            .and_then(|request| request.denied_at)
+            .is_some();
+
+            if denied {
+                let allowed_at = intr.user.created_at().unix_timestamp() + min_account_age;
+                let color = data.colors.general_error().await;
+                intr.edit_original_interaction_response(ctx, |i| {
+                    i.embed(|e| {
+                        e.title("Verification denied")
+                            .description(format!(
+                                "Your verification request has been denied by the moderators. You can verify once your account is {} old, <t:{}:F> (<t:{}:R>).",
+                                chrono::Duration::seconds(min_account_age).readable(),
+                                allowed_at,
+                                allowed_at
+                            ))
+                            .color(color)
+                    })
+                })
+                .await?;
+            } else {
+                intr.edit_original_interaction_response(ctx, |i| {
+                    i.embed(|e| {
+                        e.title("Verification pending")
+                            .description(
+                                "Your verification request is still waiting for a moderator.",
+                            )
+                            .color(color)
+                    })
+                })
+                .await?;
+            }
+            return Ok(());
+        }
+
+        // Don't leave the user stuck as pending if the moderators couldn't be asked
+        if let Err(why) = send_approval_request(ctx, &intr.user, data).await {
+            sqlx::query!(
+                r#"DELETE FROM ttc_verification_requests WHERE user_id = $1"#,
+                intr.user.id.0 as i64
+            )
+            .execute(&*data.pool)
+            .await?;
+            return Err(why);
+        }
+
+        intr.edit_original_interaction_response(ctx, |i| {
+            i.embed(|e| {
+                e.title("Verification pending")
+                    .description(format!(
+                        "Your account is newer than {}, so a moderator has to approve your verification. You will get access as soon as that happens.",
+                        chrono::Duration::seconds(min_account_age).readable()
+                    ))
+                    .color(color)
+            })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    // Send the approve and deny buttons of a verification request to the moderators
+    async fn send_approval_request(ctx: &Context, user: &User, data: &Data) -> Result<(), Error> {
+        let moderator_role = data.config.moderator_role().await?;
+        let request_color = data.colors.verification_approval_request().await;
+
+        // The requests have to reach the moderators even with the automod logs turned off
+        let channels = match data.config.verification_request_channel().await? {
+            Some(channel) => vec![channel],
+            None => data.config.conveyance_channel().await?,
+        };
+        if channels.is_empty() {
+            return Err(Error::from(
+                "No channel is set up for verification requests, please contact a moderator.",
+            ));
+        }
+
+        for channel in &channels {
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.content(format!("<@&{}>", moderator_role))
+                        .embed(|e| {
+                            e.title("Verification approval requested")
+                                .field("User", user.tag(), true)
+                                .field("UserID", user.id, true)
+                                .field("Account created", user.created_at().readable(), false)
+                                .thumbnail(user.face())
+                                .color(request_color)
+                                .timestamp(Utc::now())
+                        })
+                        .components(|c| {
+                            c.create_action_row(|a| {
+                                a.create_button(|b| {
+                                    b.label("Approve")
+                                        .custom_id(format!(
+                                            "ttc-bot-verification-approve-{}",
+                                            user.id.0
+                                        ))
+                                        .style(ButtonStyle::Success)
+                                })
+                                .create_button(|b| {
+                                    b.label("Deny")
+                                        .custom_id(format!(
+                                            "ttc-bot-verification-deny-{}",
+                                            user.id.0
+                                        ))
+                                        .style(ButtonStyle::Danger)
+                                })
                             })
                         })
-                        .await
-                    {
-                        Ok(_) => {
-                            tokio::time::sleep(Duration::from_secs(2)).await;
-
-                            let welcome_message = data.config.welcome_message().await?;
-                            let welcome_message =
-                                welcome_message.choose(&mut rand::thread_rng()).unwrap();
-                            let welcome_message =
-                                welcome_message.replace("%user%", &intr.user.mention().to_string());
-
-                            ChannelId(data.config.welcome_channel().await? as u64)
-                                .send_message(ctx, |m| m.content(welcome_message))
-                                .await?;
-                        }
-                        Err(why) => {
-                            log::error!("Unable to respond to interaction: {}", why);
-                        }
-                    }
-                }
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Log a rejected verification attempt to the conveyance channels
+    async fn log_verification_rejection(
+        ctx: &Context,
+        user: &User,
+        allowed_at: i64,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.conveyance_verification_rejected().await;
+        for channel in &data.config.log_channels(LogCategory::Automod).await? {
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Verification rejected")
+                            .description("Account is younger than the minimum account age.")
+                            .field("User", user.tag(), true)
+                            .field("UserID", user.id, true)
+                            .field("Account created", user.created_at().readable(), false)
+                            .field("Can retry", format!("<t:{}:F>", allowed_at), false)
+                            .color(color)
+                            .timestamp(Utc::now())
+                    })
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Greet a freshly verified user in the welcome channel
+    async fn send_welcome_message(
+        ctx: &Context,
+        guild_id: GuildId,
+        user: &User,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let welcome_message = data.config.welcome_message().await?;
+        let welcome_message = match welcome_message.choose(&mut rand::thread_rng()) {
+            Some(welcome_message) => welcome_message.clone(),
+            None => return Ok(()),
+        };
+
+        let member_count = ctx
+            .cache
+            .guild_field(guild_id, |guild| guild.member_count)
+            .unwrap_or_default();
+        let info = WelcomeInfo {
+            mention: user.mention().to_string(),
+            username: user.name.clone(),
+            server: guild_id.name(ctx).unwrap_or_default(),
+            member_count,
+            account_age: welcome::account_age(*user.created_at()),
+        };
+        let welcome_message = welcome::fill_welcome_template(&welcome_message, &info);
+
+        // A broken card shouldn't keep the user from being greeted
+        let card_path = if data.config.welcome_card_enabled().await? {
+            match welcome::create_welcome_card(user, member_count).await {
+                Ok(path) => Some(path),
                 Err(why) => {
-                    return command_error!("Unable to add verified role: {}", why);
+                    log::error!("Unable to create welcome card: {}", why);
+                    None
                 }
             }
         } else {
-            let color = data.colors.general_error().await;
-            // If the user has already verified tell them about it
-            intr.edit_original_interaction_response(ctx, |i| {
-                i.embed(|e: &mut CreateEmbed| {
-                    e.title("Verification failed")
-                        .description("You are already verified! You can't over-verify yourself.")
-                        .color(color)
-                })
+            None
+        };
+
+        ChannelId(data.config.welcome_channel().await? as u64)
+            .send_message(ctx, |m| {
+                m.content(welcome_message);
+                if let Some(path) = &card_path {
+                    m.add_file(path.as_str());
+                }
+                m
             })
             .await?;
+
+        if let Some(path) = &card_path {
+            welcome::remove_welcome_card(path);
         }
+
         Ok(())
     }
 
-    // Interaction for the self role menu
+    // Interaction for the self role menus
     pub async fn self_role_menu(
         ctx: &Context,
         intr: MessageComponentInteraction,
@@ -252,19 +1336,62 @@ mod interaction_fns {
                 })
                 .await?;
 
-                // Get the available self roles from the afformentioned
-                // component
-                let available_self_roles: Vec<RoleId> = menu
-                    .options
+                let selfroles = data.config.selfroles().await?;
+
+                // The menus of a group only offer the roles currently in the group, the old
+                // menu offers whatever it was created with
+                let group_id = match intr.data.custom_id.strip_prefix("ttc-bot-self-role-menu-") {
+                    Some(group_id) => Some(group_id.parse::<i32>()?),
+                    None => None,
+                };
+                let available_self_roles: Vec<RoleId> = match group_id {
+                    Some(group_id) => selfroles
+                        .iter()
+                        .filter(|role| role.2 == Some(group_id))
+                        .map(|role| RoleId(role.0 as u64))
+                        .collect(),
+                    None => menu
+                        .options
+                        .iter()
+                        .map(|option| option.value.parse::<RoleId>().unwrap())
+                        .collect(),
+                };
+                let exclusive = match group_id {
+                    Some(group_id) => data
+                        .config
+                        .selfrole_groups()
+                        .await?
+                        .iter()
+                        .any(|group| group.0 == group_id && group.3),
+                    None => false,
+                };
+                let required_roles: HashMap<RoleId, RoleId> = selfroles
                     .iter()
-                    .map(|option| option.value.parse::<RoleId>().unwrap())
+                    .filter_map(|role| {
+                        role.3
+                            .map(|required| (RoleId(role.0 as u64), RoleId(required as u64)))
+                    })
                     .collect();
 
+                if exclusive && intr.data.values.len() > 1 {
+                    let color = data.colors.input_error().await;
+                    intr.edit_original_interaction_response(ctx, |i| {
+                        i.embed(|e| {
+                            e.color(color)
+                                .title("Too many roles selected")
+                                .description("You can only pick one role from this group")
+                        })
+                    })
+                    .await?;
+                    return Ok(());
+                }
+
                 // Get the member from the interaction
                 let mut member = intr.member.clone().unwrap();
 
                 let mut roles_to_remove: Vec<RoleId> = Vec::new();
                 let mut roles_to_add: Vec<RoleId> = Vec::new();
+                let mut missing_requirements: Vec<(RoleId, RoleId)> = Vec::new();
 
                 // If user has the role but has not selected it, remove it.
                 for role in &available_self_roles {
@@ -277,7 +1404,13 @@ mod interaction_fns {
                     } else if !check_user_role!(ctx, intr.user, intr.guild_id.unwrap(), role)
                         && intr.data.values.contains(&role.to_string())
                     {
-                        roles_to_add.push(*role);
+                        // Some roles have to be earned before they can be picked
+                        match required_roles.get(role) {
+                            Some(required_role) if !member.roles.contains(required_role) => {
+                                missing_requirements.push((*role, *required_role));
+                            }
+                            _ => roles_to_add.push(*role),
+                        }
                     }
                 }
                 if !roles_to_add.is_empty() {
@@ -287,6 +1420,29 @@ mod interaction_fns {
                     member.remove_roles(ctx, &roles_to_remove).await?;
                 }
 
+                if !missing_requirements.is_empty() {
+                    let color = data.colors.input_warn().await;
+                    intr.edit_original_interaction_response(ctx, |i| {
+                        i.embed(|e| {
+                            e.color(color)
+                                .title("Self roles partially modified")
+                                .description(format!(
+                                    "Some roles couldn't be added because you are missing the role they require:\n{}",
+                                    missing_requirements
+                                        .iter()
+                                        .map(|(role, required_role)| format!(
+                                            "<@&{}> requires <@&{}>",
+                                            role, required_role
+                                        ))
+                                        .collect::<Vec<String>>()
+                                        .join("\n")
+                                ))
+                        })
+                    })
+                    .await?;
+                    return Ok(());
+                }
+
                 let color = data.colors.selfrole_post_edit_msg().await;
                 // Notify the user that their selection of self roles has been
                 intr.edit_original_interaction_response(ctx, |i| {
@@ -301,9 +1457,118 @@ mod interaction_fns {
             _ => {
                 // In case that for some reason a random component uses this
                 // id, should never happen but we can never be certain
-                log::warn!("Invalid component type for id \"ttc-bot-self-role-menu\"");
+                log::warn!("Invalid component type for id \"{}\"", intr.data.custom_id);
+            }
+        }
+        Ok(())
+    }
+
+    // Interaction for claiming a support ticket from its summary
+    pub async fn ticket_claim_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        // The summary message has the same id as the thread started from it
+        let thread = sqlx::query_as!(
+            SupportThread,
+            r#"SELECT * FROM ttc_support_tickets WHERE thread_id = $1"#,
+            intr.message.id.0 as i64
+        )
+        .fetch_optional(&*data.pool)
+        .await?;
+
+        let is_helper = match &intr.member {
+            Some(member) => support::is_support_helper(member, data).await?,
+            None => false,
+        };
+        let problem = match &thread {
+            None => Some("This message doesn't belong to a support ticket anymore.".to_string()),
+            Some(_) if !is_helper => Some("Only helpers can claim support tickets.".to_string()),
+            Some(thread) if thread.incident_solved => {
+                Some("This ticket has already been solved.".to_string())
             }
+            Some(thread) => thread.assignee_id.map(already_claimed),
+        };
+        if let Some(problem) = problem {
+            return ticket_claim_error(ctx, &intr, problem, data).await;
         }
+
+        // Someone else may have claimed the ticket since it was read
+        let thread_id = ChannelId(intr.message.id.0);
+        let thread = match support::claim_ticket(&data.pool, thread_id, intr.user.id).await? {
+            Some(thread) => thread,
+            None => {
+                let problem = sqlx::query!(
+                    r#"SELECT assignee_id FROM ttc_support_tickets WHERE thread_id = $1"#,
+                    intr.message.id.0 as i64
+                )
+                .fetch_optional(&*data.pool)
+                .await?
+                .and_then(|record| record.assignee_id)
+                .map_or_else(
+                    || "This ticket can't be claimed anymore.".to_string(),
+                    already_claimed,
+                );
+                return ticket_claim_error(ctx, &intr, problem, data).await;
+            }
+        };
+
+        let color = data.colors.support_info().await;
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| {
+                        e.title("Ticket claimed")
+                            .description(format!("You claimed <#{}>.", thread.thread_id))
+                            .color(color)
+                    })
+                    .flags(InteractionResponseFlags::EPHEMERAL)
+                })
+        })
+        .await?;
+        ChannelId(thread.thread_id as u64)
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title("Ticket claimed")
+                        .description(format!("<@{}> has claimed this ticket.", intr.user.id))
+                        .color(color)
+                })
+            })
+            .await?;
+        support::show_ticket_assignee(ctx, &thread).await;
+
+        Ok(())
+    }
+
+    // The reason a ticket can't be claimed when someone already did
+    fn already_claimed(assignee_id: i64) -> String {
+        format!(
+            "This ticket has already been claimed by <@{}>, it can be reassigned with ``/ticket assign``.",
+            assignee_id
+        )
+    }
+
+    // Tell the helper why they couldn't claim the ticket
+    async fn ticket_claim_error(
+        ctx: &Context,
+        intr: &MessageComponentInteraction,
+        problem: String,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.input_error().await;
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| {
+                        e.title("Unable to claim ticket")
+                            .description(problem)
+                            .color(color)
+                    })
+                    .flags(InteractionResponseFlags::EPHEMERAL)
+                })
+        })
+        .await?;
         Ok(())
     }
 
@@ -414,11 +1679,20 @@ mod interaction_fns {
             .send_message(ctx, |m| {
                 m.embed(|e| {
                     e.title(&title)
-                        .field("Description", description, false)
-                        .field("System info", system_info, false)
+                        .field("Description", &description, false)
+                        .field("System info", &system_info, false)
                         .author(|a| a.name(user_name).icon_url(intr.user.face()))
                         .color(color)
                 })
+                .components(|c| {
+                    c.create_action_row(|a| {
+                        a.create_button(|b| {
+                            b.label("Claim")
+                                .style(ButtonStyle::Primary)
+                                .custom_id("ttc-bot-ticket-claim")
+                        })
+                    })
+                })
             })
             .await?;
 
@@ -433,13 +1707,15 @@ mod interaction_fns {
 
         let db_thread = match sqlx::query_as!(
             SupportThread,
-            r#"INSERT INTO ttc_support_tickets (thread_id, user_id, incident_time, incident_title, incident_solved, unarchivals) VALUES($1, $2, $3, $4, $5, $6) RETURNING *"#,
+            r#"INSERT INTO ttc_support_tickets (thread_id, user_id, incident_time, incident_title, incident_solved, unarchivals, incident_description, incident_system_info) VALUES($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *"#,
             thread.id.0 as i64,
             intr.user.id.0 as i64,
             Utc::now(),
             title,
             false,
             0,
+            description,
+            system_info,
         )
         .fetch_one(pool)
         .await {
@@ -449,9 +1725,7 @@ mod interaction_fns {
             }
         };
 
-        let mut new_title = format!("[{}] {}", db_thread.incident_id, title);
-        new_title.truncate(100);
-
+        let new_title = support::ticket_thread_name(&db_thread);
         thread.id.edit_thread(ctx, |t| t.name(&new_title)).await?;
 
         intr.edit_original_interaction_response(ctx, |i| {
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use poise::serenity_prelude::{Context, Guild, GuildId, InviteCreateEvent, Member, User};
+
+use crate::{types::data::Data, unwrap_or_return};
+
+/// The use count of an invite at the time it was last seen
+#[derive(Debug, Clone)]
+pub struct CachedInvite {
+    pub uses: u64,
+    pub max_uses: u64,
+    pub inviter: Option<User>,
+}
+
+/// The invite a member joined with
+pub struct UsedInvite {
+    pub code: String,
+    pub inviter: Option<User>,
+}
+
+/// Get the current invites of a guild with their use counts
+async fn fetch_invites(ctx: &Context, guild_id: GuildId) -> Option<HashMap<String, CachedInvite>> {
+    match guild_id.invites(ctx).await {
+        Ok(invites) => Some(
+            invites
+                .into_iter()
+                .map(|invite| {
+                    (
+                        invite.code,
+                        CachedInvite {
+                            uses: invite.uses,
+                            max_uses: invite.max_uses,
+                            inviter: invite.inviter,
+                        },
+                    )
+                })
+                .collect(),
+        ),
+        Err(why) => {
+            log::warn!("Unable to get the invites of {}: {}", guild_id, why);
+            None
+        }
+    }
+}
+
+/// Work out which invite was used by comparing the use counts from before and after a join.
+/// Returns `None` if it can't be told apart, for example when the vanity url was used.
+fn find_used_invite(
+    old: &HashMap<String, CachedInvite>,
+    new: &HashMap<String, CachedInvite>,
+) -> Option<String> {
+    let increased = new
+        .iter()
+        .filter(|(code, invite)| {
+            old.get(*code)
+                .map_or(invite.uses > 0, |old_invite| invite.uses > old_invite.uses)
+        })
+        .map(|(code, _)| code)
+        .collect::<Vec<&String>>();
+    match increased.len() {
+        1 => return Some(increased[0].clone()),
+        0 => (),
+        // Several members joined at once
+        _ => return None,
+    }
+
+    // Invites are deleted once their last use is taken
+    let used_up = old
+        .iter()
+        .filter(|(code, invite)| {
+            !new.contains_key(*code) && invite.max_uses > 0 && invite.uses + 1 >= invite.max_uses
+        })
+        .map(|(code, _)| code)
+        .collect::<Vec<&String>>();
+    match used_up.len() {
+        1 => Some(used_up[0].clone()),
+        _ => None,
+    }
+}
+
+// Remember the invites of a guild when the bot connects, so the first join can be compared to
+// them
+pub async fn guild_create(ctx: &Context, guild: &Guild, data: &Data) {
+    if let Some(invites) = fetch_invites(ctx, guild.id).await {
+        data.invites.write().await.insert(guild.id, invites);
+    }
+}
+
+// Invites created after the guild was cached haven't been used yet
+pub async fn invite_create(invite: &InviteCreateEvent, data: &Data) {
+    let guild_id = match invite.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+    data.invites
+        .write()
+        .await
+        .entry(guild_id)
+        .or_default()
+        .insert(
+            invite.code.clone(),
+            CachedInvite {
+                uses: 0,
+                max_uses: invite.max_uses,
+                inviter: invite.inviter.clone(),
+            },
+        );
+}
+
+/// Find the invite a new member joined with and store it for the invite statistics
+pub async fn member_joined(ctx: &Context, member: &Member, data: &Data) -> Option<UsedInvite> {
+    let used_invite = {
+        // Keep the lock while fetching, so members joining at the same time are compared in turn
+        let mut invites = data.invites.write().await;
+        match fetch_invites(ctx, member.guild_id).await {
+            Some(new_invites) => {
+                let used_invite = invites.get(&member.guild_id).and_then(|old_invites| {
+                    let code = find_used_invite(old_invites, &new_invites)?;
+                    // Used up invites are only in the old invites
+                    let inviter = new_invites
+                        .get(&code)
+                        .or_else(|| old_invites.get(&code))
+                        .and_then(|invite| invite.inviter.clone());
+                    Some(UsedInvite { code, inviter })
+                });
+                invites.insert(member.guild_id, new_invites);
+                used_invite
+            }
+            None => None,
+        }
+    };
+
+    // Joins with an unknown invite are stored too, so leaving can be tracked the same way
+    if let Err(why) = sqlx::query!(
+        r#"INSERT INTO ttc_invite_joins (guild_id, user_id, inviter_id, invite_code, joined_at) VALUES($1, $2, $3, $4, $5)"#,
+        member.guild_id.0 as i64,
+        member.user.id.0 as i64,
+        used_invite
+            .as_ref()
+            .and_then(|used_invite| used_invite.inviter.as_ref())
+            .map(|inviter| inviter.id.0 as i64),
+        used_invite.as_ref().map(|used_invite| used_invite.code.clone()),
+        Utc::now()
+    )
+    .execute(&*data.pool)
+    .await
+    {
+        log::error!("Error storing the invite of a new member: {}", why);
+    }
+
+    used_invite
+}
+
+// Mark the members who left, so the invite statistics can tell how many stayed
+pub async fn guild_member_removal(guild_id: &GuildId, user: &User, data: &Data) {
+    unwrap_or_return!(
+        sqlx::query!(
+            r#"UPDATE ttc_invite_joins SET left_at = $1 WHERE guild_id = $2 AND user_id = $3 AND left_at IS NULL"#,
+            Utc::now(),
+            guild_id.0 as i64,
+            user.id.0 as i64
+        )
+        .execute(&*data.pool)
+        .await,
+        "Error storing that a member left"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite(uses: u64, max_uses: u64) -> CachedInvite {
+        CachedInvite {
+            uses,
+            max_uses,
+            inviter: None,
+        }
+    }
+
+    #[test]
+    fn used_invites() {
+        let old = HashMap::from([
+            ("a".to_string(), invite(1, 0)),
+            ("b".to_string(), invite(4, 5)),
+        ]);
+
+        let new = HashMap::from([
+            ("a".to_string(), invite(2, 0)),
+            ("b".to_string(), invite(4, 5)),
+        ]);
+        assert_eq!(find_used_invite(&old, &new), Some("a".to_string()));
+
+        // The last use of an invite deletes it
+        let new = HashMap::from([("a".to_string(), invite(1, 0))]);
+        assert_eq!(find_used_invite(&old, &new), Some("b".to_string()));
+
+        // Nothing changed, like when the vanity url is used
+        assert_eq!(find_used_invite(&old, &old), None);
+
+        let new = HashMap::from([
+            ("a".to_string(), invite(2, 0)),
+            ("b".to_string(), invite(5, 5)),
+        ]);
+        assert_eq!(find_used_invite(&old, &new), None);
+    }
+}
@@ -24,8 +24,6 @@ pub async fn listener(
             deleted_message_id,
             guild_id,
         } => {
-            crate::events::conveyance::message_delete(ctx, channel_id, deleted_message_id, data)
-                .await;
             crate::events::emoji_cache::message_delete(
                 ctx,
                 guild_id,
@@ -34,14 +32,25 @@ pub async fn listener(
                 data,
             )
             .await;
+            // Conveyance waits for the audit log, so it goes last to not hold up the others. It also
+            // posts the ghost ping notices, which depend on who deleted the message.
+            crate::events::conveyance::message_delete(
+                ctx,
+                guild_id,
+                channel_id,
+                deleted_message_id,
+                data,
+            )
+            .await;
         }
         MessageDeleteBulk {
             channel_id,
             multiple_deleted_messages_ids,
-            guild_id: _,
+            guild_id,
         } => {
             crate::events::conveyance::message_delete_bulk(
                 ctx,
+                guild_id,
                 channel_id,
                 multiple_deleted_messages_ids,
                 data,
@@ -56,48 +65,141 @@ pub async fn listener(
             // IMPORTANT: conveyance should be called last since it overrides the old message in
             // the DB
             crate::events::emoji_cache::message_update(ctx, new, event, data).await;
+            crate::events::ghost_ping::message_update(ctx, event, data).await;
             crate::events::conveyance::message_update(ctx, new, event, data).await;
         }
         GuildMemberAddition { new_member } => {
             crate::events::conveyance::guild_member_addition(ctx, new_member, data).await;
+            crate::events::role_memory::guild_member_addition(ctx, new_member, data).await;
         }
         GuildMemberRemoval {
-            guild_id: _,
+            guild_id,
             user,
             member_data_if_available,
         } => {
             crate::events::conveyance::guild_member_removal(
+                ctx,
+                guild_id,
+                user,
+                member_data_if_available,
+                data,
+            )
+            .await;
+            crate::events::role_memory::guild_member_removal(
                 ctx,
                 user,
                 member_data_if_available,
                 data,
             )
             .await;
+            crate::events::invites::guild_member_removal(guild_id, user, data).await;
         }
         GuildBanAddition {
-            guild_id: _,
+            guild_id,
             banned_user,
         } => {
-            crate::events::conveyance::guild_ban_addition(ctx, banned_user, data).await;
+            crate::events::conveyance::guild_ban_addition(ctx, guild_id, banned_user, data).await;
         }
         GuildBanRemoval {
-            guild_id: _,
+            guild_id,
             unbanned_user,
         } => {
-            crate::events::conveyance::guild_ban_removal(ctx, unbanned_user, data).await;
+            crate::events::conveyance::guild_ban_removal(ctx, guild_id, unbanned_user, data).await;
         }
         GuildMemberUpdate {
             old_if_available,
             new,
         } => {
+            crate::events::conveyance::member_profile_update(ctx, old_if_available, new, data)
+                .await;
             crate::events::conveyance::guild_member_update(ctx, old_if_available, new, data).await;
         }
         InteractionCreate { interaction } => {
             crate::events::interactions::interaction_create(ctx, interaction, data).await;
         }
+        VoiceStateUpdate { old, new } => {
+            crate::events::conveyance::voice_state_update(ctx, old, new, data).await;
+        }
         ThreadUpdate { thread } => {
             crate::events::support::thread_update(ctx, thread, data).await;
         }
+        GuildCreate { guild, .. } => {
+            crate::events::server_logs::guild_create(guild, data).await;
+            crate::events::invites::guild_create(ctx, guild, data).await;
+        }
+        GuildEmojisUpdate {
+            guild_id,
+            current_state,
+        } => {
+            // The self roles compare against the emoji snapshot that server logs replaces
+            let selfroles_changed =
+                crate::events::selfroles::shown_emojis_changed(guild_id, current_state, data).await;
+            crate::events::server_logs::guild_emojis_update(ctx, guild_id, current_state, data)
+                .await;
+            if selfroles_changed {
+                crate::events::selfroles::refresh(ctx, guild_id, data).await;
+            }
+        }
+        GuildRoleCreate { new } => {
+            crate::events::server_logs::guild_role_create(ctx, new, data).await;
+        }
+        GuildRoleUpdate {
+            old_data_if_available,
+            new,
+        } => {
+            crate::events::server_logs::guild_role_update(ctx, old_data_if_available, new, data)
+                .await;
+            crate::events::selfroles::guild_role_update(ctx, old_data_if_available, new, data)
+                .await;
+        }
+        GuildRoleDelete {
+            guild_id,
+            removed_role_id,
+            removed_role_data_if_available,
+        } => {
+            crate::events::server_logs::guild_role_delete(
+                ctx,
+                removed_role_id,
+                removed_role_data_if_available,
+                data,
+            )
+            .await;
+            crate::events::selfroles::guild_role_delete(ctx, guild_id, removed_role_id, data).await;
+        }
+        ChannelCreate { channel } => {
+            crate::events::server_logs::channel_create(ctx, channel, data).await;
+        }
+        ChannelDelete { channel } => {
+            crate::events::server_logs::channel_delete(ctx, channel, data).await;
+        }
+        ChannelUpdate { old, new } => {
+            crate::events::server_logs::channel_update(ctx, old, new, data).await;
+        }
+        CategoryCreate { category } => {
+            crate::events::server_logs::category_create(ctx, category, data).await;
+        }
+        CategoryDelete { category } => {
+            crate::events::server_logs::category_delete(ctx, category, data).await;
+        }
+        ThreadCreate { thread } => {
+            crate::events::server_logs::thread_create(ctx, thread, data).await;
+        }
+        ThreadDelete { thread } => {
+            crate::events::server_logs::thread_delete(ctx, thread, data).await;
+        }
+        InviteCreate { data: invite } => {
+            crate::events::invites::invite_create(invite, data).await;
+            crate::events::server_logs::invite_create(ctx, invite, data).await;
+        }
+        InviteDelete { data: invite } => {
+            crate::events::server_logs::invite_delete(ctx, invite, data).await;
+        }
+        WebhookUpdate {
+            belongs_to_channel_id,
+            ..
+        } => {
+            crate::events::server_logs::webhook_update(ctx, belongs_to_channel_id, data).await;
+        }
 
         _ => (),
     }
@@ -0,0 +1,165 @@
+use chrono::Utc;
+use poise::serenity_prelude::{ChannelId, Context, Member, RoleId, User};
+
+use crate::{
+    types::{data::Data, log_category::LogCategory},
+    unwrap_or_return,
+};
+
+// Remember the roles of a leaving member so they can be given back when they rejoin
+pub async fn guild_member_removal(
+    _ctx: &Context,
+    user: &User,
+    member: &Option<Member>,
+    data: &Data,
+) {
+    let member = match member {
+        Some(member) => member,
+        None => {
+            log::warn!(
+                "Roles of {} not available, unable to remember them",
+                user.tag()
+            );
+            return;
+        }
+    };
+
+    let pool = &*data.pool;
+    let left_at = Utc::now();
+
+    // Only the roles from the last time they left are relevant
+    unwrap_or_return!(
+        sqlx::query!(
+            r#"DELETE FROM ttc_role_memory WHERE guild_id = $1 AND user_id = $2"#,
+            member.guild_id.0 as i64,
+            user.id.0 as i64
+        )
+        .execute(pool)
+        .await,
+        "Error clearing old role memory"
+    );
+    for role in &member.roles {
+        unwrap_or_return!(
+            sqlx::query!(
+                r#"INSERT INTO ttc_role_memory (guild_id, user_id, role_id, left_at) VALUES($1, $2, $3, $4)"#,
+                member.guild_id.0 as i64,
+                user.id.0 as i64,
+                role.0 as i64,
+                left_at
+            )
+            .execute(pool)
+            .await,
+            "Error writing role memory"
+        );
+    }
+}
+
+// Give a rejoining member the roles they had when they left
+pub async fn guild_member_addition(ctx: &Context, new_member: &Member, data: &Data) {
+    let pool = &*data.pool;
+    let remembered = unwrap_or_return!(
+        sqlx::query!(
+            r#"SELECT role_id FROM ttc_role_memory WHERE guild_id = $1 AND user_id = $2"#,
+            new_member.guild_id.0 as i64,
+            new_member.user.id.0 as i64
+        )
+        .fetch_all(pool)
+        .await,
+        "Error reading role memory"
+    );
+    if remembered.is_empty() {
+        return;
+    }
+
+    let restore_all = unwrap_or_return!(
+        data.config.role_memory_restore_all().await,
+        "Error getting role memory config"
+    );
+    let rules = unwrap_or_return!(
+        data.config.role_memory_rules().await,
+        "Error getting role memory rules"
+    );
+    let guild_roles = unwrap_or_return!(
+        new_member.guild_id.roles(ctx).await,
+        "Error getting the roles of the guild"
+    );
+
+    // Roles with a rule are always or never restored, the rest depends on the config. The ones
+    // that are always restored, like punitive roles, go first.
+    let mut roles: Vec<(RoleId, bool)> = remembered
+        .iter()
+        .filter_map(
+            |record| match rules.iter().find(|rule| rule.0 == record.role_id) {
+                Some((_, true)) => Some((RoleId(record.role_id as u64), true)),
+                Some((_, false)) => None,
+                None if restore_all => Some((RoleId(record.role_id as u64), false)),
+                None => None,
+            },
+        )
+        .filter(|(role, _)| match guild_roles.get(role) {
+            // Roles of integrations can't be given out by hand
+            Some(role) => !role.managed,
+            None => false,
+        })
+        .collect();
+    roles.sort_by_key(|(_, always)| !always);
+
+    // Give the roles out one by one, so a single role the bot can't assign doesn't stop the rest
+    let mut member = new_member.clone();
+    let mut restored = Vec::new();
+    for (role, _) in roles {
+        match member.add_role(ctx, role).await {
+            Ok(_) => restored.push(role),
+            Err(why) => log::warn!(
+                "Unable to restore role {} of {}: {}",
+                role,
+                new_member.user.tag(),
+                why
+            ),
+        }
+    }
+
+    // The memory is cleared even if the roles couldn't be logged
+    unwrap_or_return!(
+        sqlx::query!(
+            r#"DELETE FROM ttc_role_memory WHERE guild_id = $1 AND user_id = $2"#,
+            new_member.guild_id.0 as i64,
+            new_member.user.id.0 as i64
+        )
+        .execute(pool)
+        .await,
+        "Error clearing role memory"
+    );
+
+    if restored.is_empty() {
+        return;
+    }
+
+    let conv_channels = unwrap_or_return!(
+        data.config.log_channels(LogCategory::Moderation).await,
+        "Error getting log channels"
+    );
+    let color = data.colors.conveyance_roles_restored().await;
+    let roles_string = restored
+        .iter()
+        .map(|role| format!("<@&{}>", role))
+        .collect::<Vec<String>>()
+        .join(" ");
+    for channel in &conv_channels {
+        unwrap_or_return!(
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| {
+                    m.embed(|e| {
+                        e.title("Roles restored")
+                            .color(color)
+                            .field("User", new_member.user.tag(), true)
+                            .field("UserID", new_member.user.id, true)
+                            .field("Roles", &roles_string, false)
+                            .timestamp(Utc::now())
+                    })
+                })
+                .await,
+            "Error sending message"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::{collections::HashMap, time::Duration};
+
+use poise::serenity_prelude::{Context, Emoji, EmojiId, GuildId, Role, RoleId};
+
+use crate::{types::data::Data, unwrap_or_return, utils::selfroles};
+
+// How long to wait for more changes before refreshing, moving roles around sends an update for
+// every role that moved
+const REFRESH_DELAY: Duration = Duration::from_secs(5);
+
+/// Check whether an emoji shown in the menus was added, renamed or removed. Has to run before the
+/// emoji snapshot is replaced with the current emojis.
+pub async fn shown_emojis_changed(
+    guild_id: &GuildId,
+    current_state: &HashMap<EmojiId, Emoji>,
+    data: &Data,
+) -> bool {
+    let selfroles = match data.config.selfroles().await {
+        Ok(selfroles) => selfroles,
+        Err(why) => {
+            log::error!("Unable to get the self roles: {}", why);
+            return false;
+        }
+    };
+
+    // Emojis are looked up by name, so only a change of which emoji a name points to matters
+    let emoji_by_name = |emojis: &HashMap<EmojiId, Emoji>, name: &str| {
+        emojis
+            .values()
+            .find(|emoji| emoji.name == name)
+            .map(|emoji| emoji.id)
+    };
+    match data.emoji_snapshots.read().await.get(guild_id) {
+        Some(old_state) => selfroles
+            .iter()
+            .filter_map(|role| role.1.as_deref())
+            .any(|name| emoji_by_name(old_state, name) != emoji_by_name(current_state, name)),
+        None => true,
+    }
+}
+
+/// Refresh the menus if a self role or a role required by one was renamed
+pub async fn guild_role_update(ctx: &Context, old: &Option<Role>, new: &Role, data: &Data) {
+    if let Some(old) = old {
+        if old.name == new.name {
+            return;
+        }
+    }
+    if is_shown_role(data, new.id).await {
+        refresh(ctx, &new.guild_id, data).await;
+    }
+}
+
+/// Refresh the menus if a self role or a role required by one was deleted
+pub async fn guild_role_delete(ctx: &Context, guild_id: &GuildId, role_id: &RoleId, data: &Data) {
+    if is_shown_role(data, *role_id).await {
+        refresh(ctx, guild_id, data).await;
+    }
+}
+
+/// Check whether a role shows up in the self role menus
+async fn is_shown_role(data: &Data, role_id: RoleId) -> bool {
+    let selfroles = match data.config.selfroles().await {
+        Ok(selfroles) => selfroles,
+        Err(why) => {
+            log::error!("Unable to get the self roles: {}", why);
+            return false;
+        }
+    };
+
+    let role_id = role_id.0 as i64;
+    selfroles
+        .iter()
+        .any(|role| role.0 == role_id || role.3 == Some(role_id))
+}
+
+/// Refresh the menus of a guild once no more changes came in for a moment
+pub async fn refresh(ctx: &Context, guild_id: &GuildId, data: &Data) {
+    // A refresh is already waiting and will pick up this change as well
+    if !data.selfrole_refreshes.write().await.insert(*guild_id) {
+        return;
+    }
+    tokio::time::sleep(REFRESH_DELAY).await;
+    data.selfrole_refreshes.write().await.remove(guild_id);
+
+    let report = unwrap_or_return!(
+        selfroles::refresh_selfrole_menus(ctx, *guild_id, &data.pool, &data.config, &data.colors)
+            .await,
+        "Unable to refresh the self role menus"
+    );
+    report.log_problems();
+}
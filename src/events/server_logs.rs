@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use poise::serenity_prelude::{
+    Channel, ChannelCategory, ChannelId, Color, Context, CreateEmbed, Emoji, EmojiId, Guild,
+    GuildChannel, GuildId, InviteCreateEvent, InviteDeleteEvent, PartialGuildChannel,
+    PermissionOverwrite, PermissionOverwriteType, Permissions, Role, RoleId,
+};
+
+use crate::{
+    types::{data::Data, log_category::LogCategory},
+    unwrap_or_return,
+};
+
+// Discord doesn't allow longer embed field values
+const FIELD_LIMIT: usize = 1024;
+
+// ----------------------------------------
+// Logging of structural changes to servers
+// ----------------------------------------
+
+/// Send a server update log, unless the channel it happened in is blacklisted
+async fn log_server_update(
+    ctx: &Context,
+    data: &Data,
+    channel_id: Option<ChannelId>,
+    embed: CreateEmbed,
+) {
+    if let Some(channel_id) = channel_id {
+        if unwrap_or_return!(
+            data.config
+                .log_blacklist_channels(LogCategory::ServerUpdates)
+                .await,
+            "Error getting blacklisted log channels"
+        )
+        .contains(&(channel_id.0 as i64))
+        {
+            return;
+        }
+    }
+
+    let log_channels = unwrap_or_return!(
+        data.config.log_channels(LogCategory::ServerUpdates).await,
+        "Error getting log channels"
+    );
+    for channel in &log_channels {
+        unwrap_or_return!(
+            ChannelId(*channel as u64)
+                .send_message(ctx, |m| m.set_embed(embed.clone()))
+                .await,
+            "Error sending message"
+        );
+    }
+}
+
+/// Create the base of a server update embed
+fn server_update_embed(title: &str, color: Color) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title(title).color(color).timestamp(Utc::now());
+    embed
+}
+
+/// Cut a field value down to what fits into an embed
+fn field_value(value: String) -> String {
+    if value.is_empty() {
+        "None".to_string()
+    } else if value.chars().count() > FIELD_LIMIT {
+        format!(
+            "{}...",
+            value.chars().take(FIELD_LIMIT - 3).collect::<String>()
+        )
+    } else {
+        value
+    }
+}
+
+/// List the permissions that were added and removed between two permission sets
+pub fn permission_diff(old: Permissions, new: Permissions) -> String {
+    let mut lines = Vec::new();
+    let added = (new - old).get_permission_names();
+    if !added.is_empty() {
+        lines.push(format!("Added: {}", added.join(", ")));
+    }
+    let removed = (old - new).get_permission_names();
+    if !removed.is_empty() {
+        lines.push(format!("Removed: {}", removed.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// Describe how the permission overwrites of a channel changed, one line per role or member
+fn overwrite_diff(
+    guild_id: GuildId,
+    old: &[PermissionOverwrite],
+    new: &[PermissionOverwrite],
+) -> String {
+    let mut kinds: Vec<PermissionOverwriteType> = old.iter().map(|o| o.kind).collect();
+    for overwrite in new {
+        if !kinds.contains(&overwrite.kind) {
+            kinds.push(overwrite.kind);
+        }
+    }
+
+    let mut lines = Vec::new();
+    for kind in kinds {
+        let target = match kind {
+            // The @everyone role has the same id as the guild
+            PermissionOverwriteType::Role(role) if role.0 == guild_id.0 => "@everyone".to_string(),
+            PermissionOverwriteType::Role(role) => format!("<@&{}>", role),
+            PermissionOverwriteType::Member(user) => format!("<@{}>", user),
+            _ => "Unknown".to_string(),
+        };
+        let old = old.iter().find(|o| o.kind == kind);
+        let new = new.iter().find(|o| o.kind == kind);
+        match (old, new) {
+            (None, Some(new)) => lines.push(format!(
+                "{}: overwrite added\n{}",
+                target,
+                overwrite_summary(new)
+            )),
+            (Some(_), None) => lines.push(format!("{}: overwrite removed", target)),
+            (Some(old), Some(new)) if old != new => {
+                let mut changes = Vec::new();
+                let allow = permission_diff(old.allow, new.allow);
+                if !allow.is_empty() {
+                    changes.push(format!("Allowed {}", allow.replace('\n', "\nAllowed ")));
+                }
+                let deny = permission_diff(old.deny, new.deny);
+                if !deny.is_empty() {
+                    changes.push(format!("Denied {}", deny.replace('\n', "\nDenied ")));
+                }
+                lines.push(format!("{}:\n{}", target, changes.join("\n")));
+            }
+            _ => (),
+        }
+    }
+    lines.join("\n")
+}
+
+/// List the allowed and denied permissions of a single overwrite
+fn overwrite_summary(overwrite: &PermissionOverwrite) -> String {
+    let mut lines = Vec::new();
+    if !overwrite.allow.is_empty() {
+        lines.push(format!(
+            "Allowed: {}",
+            overwrite.allow.get_permission_names().join(", ")
+        ));
+    }
+    if !overwrite.deny.is_empty() {
+        lines.push(format!(
+            "Denied: {}",
+            overwrite.deny.get_permission_names().join(", ")
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Add a field showing the old and new value if they are different
+fn changed_field<T: PartialEq + std::fmt::Display>(
+    embed: &mut CreateEmbed,
+    name: &str,
+    old: T,
+    new: T,
+) -> bool {
+    if old == new {
+        return false;
+    }
+    embed.field(name, field_value(format!("{} → {}", old, new)), false);
+    true
+}
+
+/// Format an optional value for the change fields
+fn or_none<T: std::fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "None".to_string(),
+    }
+}
+
+pub async fn channel_create(ctx: &Context, channel: &GuildChannel, data: &Data) {
+    let mut embed = server_update_embed(
+        "Channel created",
+        data.colors.conveyance_channel_create().await,
+    );
+    embed
+        .field("Channel", format!("<#{}>", channel.id), true)
+        .field("Name", &channel.name, true)
+        .field("ChannelID", channel.id, true)
+        .field("Type", channel.kind.name(), true);
+    if let Some(parent_id) = channel.parent_id {
+        embed.field("Category", format!("<#{}>", parent_id), true);
+    }
+    log_server_update(ctx, data, Some(channel.id), embed).await;
+}
+
+pub async fn channel_delete(ctx: &Context, channel: &GuildChannel, data: &Data) {
+    let mut embed = server_update_embed(
+        "Channel deleted",
+        data.colors.conveyance_channel_delete().await,
+    );
+    embed
+        .field("Name", &channel.name, true)
+        .field("ChannelID", channel.id, true)
+        .field("Type", channel.kind.name(), true);
+    log_server_update(ctx, data, Some(channel.id), embed).await;
+}
+
+pub async fn category_create(ctx: &Context, category: &ChannelCategory, data: &Data) {
+    let mut embed = server_update_embed(
+        "Category created",
+        data.colors.conveyance_channel_create().await,
+    );
+    embed
+        .field("Name", &category.name, true)
+        .field("ChannelID", category.id, true);
+    log_server_update(ctx, data, Some(category.id), embed).await;
+}
+
+pub async fn category_delete(ctx: &Context, category: &ChannelCategory, data: &Data) {
+    let mut embed = server_update_embed(
+        "Category deleted",
+        data.colors.conveyance_channel_delete().await,
+    );
+    embed
+        .field("Name", &category.name, true)
+        .field("ChannelID", category.id, true);
+    log_server_update(ctx, data, Some(category.id), embed).await;
+}
+
+pub async fn channel_update(ctx: &Context, old: &Option<Channel>, new: &Channel, data: &Data) {
+    let mut embed = server_update_embed(
+        "Channel updated",
+        data.colors.conveyance_channel_update().await,
+    );
+
+    // Only the values shown here are compared, so changes to the position alone aren't logged
+    let (channel_id, changed) = match (old, new) {
+        (Some(Channel::Guild(old)), Channel::Guild(new)) => {
+            embed
+                .field("Channel", format!("<#{}>", new.id), true)
+                .field("ChannelID", new.id, true);
+            let mut changed = changed_field(&mut embed, "Name", &old.name, &new.name);
+            changed |= changed_field(
+                &mut embed,
+                "Topic",
+                or_none(&old.topic),
+                or_none(&new.topic),
+            );
+            changed |= changed_field(&mut embed, "NSFW", old.nsfw, new.nsfw);
+            changed |= changed_field(
+                &mut embed,
+                "Slowmode (seconds)",
+                old.rate_limit_per_user.unwrap_or(0),
+                new.rate_limit_per_user.unwrap_or(0),
+            );
+            changed |= changed_field(
+                &mut embed,
+                "Category",
+                or_none(&old.parent_id.map(|id| format!("<#{}>", id))),
+                or_none(&new.parent_id.map(|id| format!("<#{}>", id))),
+            );
+            let overwrites = overwrite_diff(
+                new.guild_id,
+                &old.permission_overwrites,
+                &new.permission_overwrites,
+            );
+            if !overwrites.is_empty() {
+                embed.field("Permission overwrites", field_value(overwrites), false);
+                changed = true;
+            }
+            (new.id, changed)
+        }
+        (Some(Channel::Category(old)), Channel::Category(new)) => {
+            embed
+                .field("Category", &new.name, true)
+                .field("ChannelID", new.id, true);
+            let mut changed = changed_field(&mut embed, "Name", &old.name, &new.name);
+            let overwrites = overwrite_diff(
+                new.guild_id,
+                &old.permission_overwrites,
+                &new.permission_overwrites,
+            );
+            if !overwrites.is_empty() {
+                embed.field("Permission overwrites", field_value(overwrites), false);
+                changed = true;
+            }
+            (new.id, changed)
+        }
+        (None, _) => {
+            log::debug!("Channel updated, but the old channel isn't cached");
+            return;
+        }
+        _ => return,
+    };
+
+    if changed {
+        log_server_update(ctx, data, Some(channel_id), embed).await;
+    }
+}
+
+pub async fn guild_role_create(ctx: &Context, role: &Role, data: &Data) {
+    let mut embed = server_update_embed("Role created", data.colors.conveyance_role_create().await);
+    embed
+        .field("Role", format!("<@&{}>", role.id), true)
+        .field("Name", &role.name, true)
+        .field("RoleID", role.id, true)
+        .field(
+            "Permissions",
+            field_value(role.permissions.get_permission_names().join(", ")),
+            false,
+        );
+    log_server_update(ctx, data, None, embed).await;
+}
+
+pub async fn guild_role_delete(ctx: &Context, role_id: &RoleId, role: &Option<Role>, data: &Data) {
+    let mut embed = server_update_embed("Role deleted", data.colors.conveyance_role_delete().await);
+    embed.field(
+        "Name",
+        match role {
+            Some(role) => role.name.as_str(),
+            None => "Not available",
+        },
+        true,
+    );
+    embed.field("RoleID", role_id, true);
+    log_server_update(ctx, data, None, embed).await;
+}
+
+pub async fn guild_role_update(ctx: &Context, old: &Option<Role>, new: &Role, data: &Data) {
+    let old = match old {
+        Some(old) => old,
+        None => {
+            log::debug!("Role updated, but the old role isn't cached");
+            return;
+        }
+    };
+
+    let mut embed = server_update_embed("Role updated", data.colors.conveyance_role_update().await);
+    embed
+        .field("Role", format!("<@&{}>", new.id), true)
+        .field("RoleID", new.id, true);
+    let mut changed = changed_field(&mut embed, "Name", &old.name, &new.name);
+    changed |= changed_field(
+        &mut embed,
+        "Color",
+        format!("#{}", old.colour.hex()),
+        format!("#{}", new.colour.hex()),
+    );
+    changed |= changed_field(&mut embed, "Hoisted", old.hoist, new.hoist);
+    changed |= changed_field(&mut embed, "Mentionable", old.mentionable, new.mentionable);
+    let permissions = permission_diff(old.permissions, new.permissions);
+    if !permissions.is_empty() {
+        embed.field("Permissions", field_value(permissions), false);
+        changed = true;
+    }
+
+    // Moving roles around updates the position of every role below, those aren't logged
+    if changed {
+        log_server_update(ctx, data, None, embed).await;
+    }
+}
+
+pub async fn thread_create(ctx: &Context, thread: &GuildChannel, data: &Data) {
+    // The event is also sent when the bot is added to an existing private thread
+    if let Some(metadata) = &thread.thread_metadata {
+        if let Some(created_at) = metadata.create_timestamp {
+            if Utc::now().timestamp() - created_at.unix_timestamp() > 60 {
+                return;
+            }
+        }
+    }
+
+    let mut embed = server_update_embed(
+        "Thread created",
+        data.colors.conveyance_thread_create().await,
+    );
+    embed
+        .field("Thread", format!("<#{}>", thread.id), true)
+        .field("Name", &thread.name, true)
+        .field("ThreadID", thread.id, true);
+    if let Some(parent_id) = thread.parent_id {
+        embed.field("Channel", format!("<#{}>", parent_id), true);
+    }
+    log_server_update(ctx, data, thread.parent_id.or(Some(thread.id)), embed).await;
+}
+
+pub async fn thread_delete(ctx: &Context, thread: &PartialGuildChannel, data: &Data) {
+    let mut embed = server_update_embed(
+        "Thread deleted",
+        data.colors.conveyance_thread_delete().await,
+    );
+    embed.field("ThreadID", thread.id, true).field(
+        "Channel",
+        format!("<#{}>", thread.parent_id),
+        true,
+    );
+    log_server_update(ctx, data, Some(thread.parent_id), embed).await;
+}
+
+/// Remember the emojis of a guild, so changes to them can be shown later
+pub async fn guild_create(guild: &Guild, data: &Data) {
+    data.emoji_snapshots
+        .write()
+        .await
+        .insert(guild.id, guild.emojis.clone());
+}
+
+pub async fn guild_emojis_update(
+    ctx: &Context,
+    guild_id: &GuildId,
+    current_state: &HashMap<EmojiId, Emoji>,
+    data: &Data,
+) {
+    let old_state = data
+        .emoji_snapshots
+        .write()
+        .await
+        .insert(*guild_id, current_state.clone());
+    let old_state = match old_state {
+        Some(old_state) => old_state,
+        None => {
+            log::debug!("Emojis updated, but the old emojis aren't known");
+            return;
+        }
+    };
+
+    let added = current_state
+        .values()
+        .filter(|emoji| !old_state.contains_key(&emoji.id))
+        .map(|emoji| format!("{} `{}`", emoji, emoji.name))
+        .collect::<Vec<String>>();
+    let removed = old_state
+        .values()
+        .filter(|emoji| !current_state.contains_key(&emoji.id))
+        .map(|emoji| format!("`{}`", emoji.name))
+        .collect::<Vec<String>>();
+    let renamed = current_state
+        .values()
+        .filter_map(|emoji| {
+            old_state
+                .get(&emoji.id)
+                .filter(|old| old.name != emoji.name)
+                .map(|old| format!("{} `{}` → `{}`", emoji, old.name, emoji.name))
+        })
+        .collect::<Vec<String>>();
+
+    if added.is_empty() && removed.is_empty() && renamed.is_empty() {
+        return;
+    }
+
+    let mut embed = server_update_embed(
+        "Emojis updated",
+        data.colors.conveyance_emojis_update().await,
+    );
+    for (name, list) in [("Added", added), ("Removed", removed), ("Renamed", renamed)] {
+        if !list.is_empty() {
+            embed.field(name, field_value(list.join("\n")), false);
+        }
+    }
+    log_server_update(ctx, data, None, embed).await;
+}
+
+pub async fn invite_create(ctx: &Context, invite: &InviteCreateEvent, data: &Data) {
+    let mut embed = server_update_embed(
+        "Invite created",
+        data.colors.conveyance_invite_create().await,
+    );
+    embed
+        .field("Code", &invite.code, true)
+        .field("Channel", format!("<#{}>", invite.channel_id), true)
+        .field(
+            "Created by",
+            match &invite.inviter {
+                Some(inviter) => format!("{} ({})", inviter.tag(), inviter.id),
+                None => "Not available".to_string(),
+            },
+            false,
+        )
+        .field(
+            "Max uses",
+            match invite.max_uses {
+                0 => "Unlimited".to_string(),
+                uses => uses.to_string(),
+            },
+            true,
+        )
+        .field(
+            "Expires",
+            match invite.max_age {
+                0 => "Never".to_string(),
+                age => format!("<t:{}:R>", Utc::now().timestamp() + age as i64),
+            },
+            true,
+        )
+        .field("Temporary membership", invite.temporary, true);
+    log_server_update(ctx, data, Some(invite.channel_id), embed).await;
+}
+
+pub async fn invite_delete(ctx: &Context, invite: &InviteDeleteEvent, data: &Data) {
+    let mut embed = server_update_embed(
+        "Invite deleted",
+        data.colors.conveyance_invite_delete().await,
+    );
+    embed.field("Code", &invite.code, true).field(
+        "Channel",
+        format!("<#{}>", invite.channel_id),
+        true,
+    );
+    log_server_update(ctx, data, Some(invite.channel_id), embed).await;
+}
+
+pub async fn webhook_update(ctx: &Context, channel_id: &ChannelId, data: &Data) {
+    // The event doesn't say what changed, so show the webhooks the channel has now
+    let webhooks = match channel_id.webhooks(ctx).await {
+        Ok(webhooks) => webhooks
+            .iter()
+            .map(|webhook| match &webhook.name {
+                Some(name) => format!("{} ({})", name, webhook.id),
+                None => webhook.id.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Err(why) => {
+            log::warn!("Unable to get the webhooks of {}: {}", channel_id, why);
+            "Not available".to_string()
+        }
+    };
+
+    let mut embed = server_update_embed(
+        "Webhooks updated",
+        data.colors.conveyance_webhooks_update().await,
+    );
+    embed
+        .field("Channel", format!("<#{}>", channel_id), true)
+        .field("Current webhooks", field_value(webhooks), false);
+    log_server_update(ctx, data, Some(*channel_id), embed).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_diff() {
+        assert_eq!(
+            permission_diff(
+                Permissions::SEND_MESSAGES | Permissions::MANAGE_CHANNELS,
+                Permissions::SEND_MESSAGES | Permissions::ADMINISTRATOR
+            ),
+            "Added: Administrator\nRemoved: Manage Channels"
+        );
+        assert_eq!(
+            permission_diff(Permissions::SEND_MESSAGES, Permissions::SEND_MESSAGES),
+            ""
+        );
+    }
+}
@@ -11,12 +11,25 @@ mod commands {
     pub mod support;
 }
 mod utils {
+    pub mod attachment_archive;
+    pub mod audit_log;
     pub mod autocomplete_functions;
     pub mod bee_utils;
+    pub mod captcha;
+    pub mod diff;
     pub mod emoji_cache;
+    pub mod event_log;
+    pub mod event_store;
     pub mod helper_functions;
+    pub mod impersonation;
     pub mod macros;
+    pub mod message_cache;
+    pub mod pagination;
+    pub mod selfroles;
+    pub mod temp_roles;
+    pub mod user_data;
     pub mod userinfo;
+    pub mod welcome;
 }
 mod events {
     pub mod bee;
@@ -24,14 +37,22 @@ mod events {
     pub mod conveyance;
     pub mod easter_egg;
     pub mod emoji_cache;
+    pub mod ghost_ping;
     pub mod interactions;
+    pub mod invites;
     pub mod listener;
+    pub mod role_memory;
+    pub mod selfroles;
+    pub mod server_logs;
     pub mod support;
 }
 mod types {
     pub mod colors;
     pub mod config;
     pub mod data;
+    pub mod event_type;
+    pub mod log_category;
+    pub mod message_state;
 }
 mod traits {
     pub mod context_ext;
@@ -55,6 +76,7 @@ use std::io::Read;
 use std::time::Instant;
 use std::{collections::HashSet, fs::File, sync::Arc};
 use types::{colors::Colors, config::Config, data::Data};
+use utils::event_log::{EventLog, EventLogConfig};
 
 // Context and error types to be used in the crate
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -218,6 +240,10 @@ async fn main() {
         owners.insert(poise::serenity_prelude::UserId(owner.as_u64().unwrap()));
     }
 
+    // Start writing the conveyance events to disk if the event log is configured
+    let event_log = EventLogConfig::from_yaml(&config["event_log"])
+        .map(|event_log_config| EventLog::start(event_log_config).unwrap());
+
     // Create the connection to the database
     let pool = PgPoolOptions::new()
         .max_connections(5)
@@ -281,11 +307,42 @@ async fn main() {
                 let config = Config::new(Arc::clone(&pool));
                 let colors = Colors::new(Arc::clone(&pool));
 
+                // Keep the posted self role menus in sync with the database
+                tokio::spawn(utils::selfroles::selfrole_change_listener(
+                    ctx.clone(),
+                    Arc::clone(&pool),
+                ));
+                // Take back temporary roles once they expire
+                tokio::spawn(utils::temp_roles::temp_role_expiry_task(
+                    ctx.clone(),
+                    Arc::clone(&pool),
+                ));
+                // Remove old messages from the message cache
+                tokio::spawn(utils::message_cache::message_cache_cleanup_task(
+                    Arc::clone(&pool),
+                ));
+                // Remove events older than the configured retention from the event store
+                tokio::spawn(utils::event_store::event_store_cleanup_task(
+                    Arc::clone(&pool),
+                ));
+                // Keep the attachment archive within its age and size limits
+                tokio::spawn(utils::attachment_archive::attachment_archive_prune_task(
+                    Arc::clone(&pool),
+                ));
+
                 Ok(Data {
                     harold_message: RwLock::new(None),
                     beeified_users: RwLock::new(HashMap::new()),
                     beezone_channels: RwLock::new(HashMap::new()),
                     webhooks: RwLock::new(webhooks),
+                    captchas: RwLock::new(HashMap::new()),
+                    onboarding_users: RwLock::new(HashSet::new()),
+                    emoji_snapshots: RwLock::new(HashMap::new()),
+                    invites: RwLock::new(HashMap::new()),
+                    audit_log_claims: RwLock::new(HashMap::new()),
+                    selfrole_refreshes: RwLock::new(HashSet::new()),
+                    ghost_ping_notices: RwLock::new(HashMap::new()),
+                    event_log,
                     pool,
                     thread_name_regex: Regex::new("[^a-zA-Z0-9 ]").unwrap(),
                     startup_time: Instant::now(),
@@ -301,8 +358,10 @@ async fn main() {
                 commands::admin::shutdown(),
                 commands::admin::create_verification(),
                 commands::admin::create_selfroles(),
+                commands::admin::selfroles(),
                 commands::admin::create_support_ticket_button(),
                 commands::admin::rebuild_emoji_cache(),
+                commands::admin::backfill_tickets(),
                 // General commands
                 commands::general::ping(),
                 commands::general::version(),
@@ -310,6 +369,8 @@ async fn main() {
                 commands::general::userinfo_ctxmenu(),
                 commands::general::serverinfo(),
                 commands::general::leaderboard(),
+                commands::general::invites(),
+                commands::general::mydata(),
                 commands::general::help(),
                 // Localisation commands
                 commands::localisation::translate(),
@@ -326,9 +387,14 @@ async fn main() {
                 commands::moderation::beezone(),
                 commands::moderation::unbeezone(),
                 commands::moderation::idban(),
+                commands::moderation::temprole(),
+                commands::moderation::rolememory(),
+                commands::moderation::history(),
+                commands::moderation::messagelog(),
                 // Support commands
                 commands::support::solve(),
                 commands::support::search(),
+                commands::support::ticket(),
             ],
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some("ttc!".to_string()),
@@ -72,6 +72,8 @@ impl Colors {
     embed_color!(admin_success, Color::FOOYOO);
     embed_color!(mod_success, Color::FOOYOO);
     embed_color!(mod_punish, Color::RED);
+    embed_color!(mod_history, Color::BLURPLE);
+    embed_color!(mod_message_log, Color::BLURPLE);
 
     // Conveyance
     embed_color!(conveyance_msg_delete, Color::GOLD);
@@ -81,9 +83,37 @@ impl Colors {
     embed_color!(conveyance_member_update, Color::ORANGE);
     embed_color!(conveyance_ban_addition, Color::DARK_RED);
     embed_color!(conveyance_unban, Color::FOOYOO);
+    embed_color!(conveyance_verification_rejected, Color::ORANGE);
+    embed_color!(conveyance_captcha_failed, Color::ORANGE);
+    embed_color!(conveyance_onboarding_answers, Color::BLURPLE);
+    embed_color!(conveyance_temp_role_added, Color::FOOYOO);
+    embed_color!(conveyance_temp_role_removed, Color::ORANGE);
+    embed_color!(conveyance_roles_restored, Color::BLURPLE);
+    embed_color!(conveyance_voice_join, Color::FOOYOO);
+    embed_color!(conveyance_voice_leave, Color::RED);
+    embed_color!(conveyance_voice_move, Color::BLURPLE);
+    embed_color!(conveyance_channel_create, Color::FOOYOO);
+    embed_color!(conveyance_channel_delete, Color::RED);
+    embed_color!(conveyance_channel_update, Color::ORANGE);
+    embed_color!(conveyance_role_create, Color::FOOYOO);
+    embed_color!(conveyance_role_delete, Color::RED);
+    embed_color!(conveyance_role_update, Color::ORANGE);
+    embed_color!(conveyance_thread_create, Color::FOOYOO);
+    embed_color!(conveyance_thread_delete, Color::RED);
+    embed_color!(conveyance_emojis_update, Color::ORANGE);
+    embed_color!(conveyance_invite_create, Color::BLURPLE);
+    embed_color!(conveyance_invite_delete, Color::DARK_GOLD);
+    embed_color!(conveyance_webhooks_update, Color::ORANGE);
+    embed_color!(conveyance_profile_update, Color::ORANGE);
+    embed_color!(ghost_ping, Color::ORANGE);
+    embed_color!(impersonation_alert, Color::RED);
 
     // Interactions
     embed_color!(verify_color, Color::FOOYOO);
+    embed_color!(verification_pending, Color::ORANGE);
+    embed_color!(verification_approval_request, Color::BLURPLE);
+    embed_color!(captcha, Color::BLURPLE);
+    embed_color!(onboarding_rules, Color::BLURPLE);
     embed_color!(selfrole_selection, Color::PURPLE);
     embed_color!(selfrole_post_edit_msg, Color::FOOYOO);
     embed_color!(ticket_has_already_ticket, Color::PURPLE);
@@ -105,4 +135,6 @@ impl Colors {
     embed_color!(emoji_info, Color::FOOYOO);
     embed_color!(emoji_cache_inaccessible, Color::RED);
     embed_color!(bee_translate_block, Color::KERBAL);
+    embed_color!(invite_stats, Color::BLURPLE);
+    embed_color!(user_data, Color::BLURPLE);
 }
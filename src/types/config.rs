@@ -1,6 +1,8 @@
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::types::log_category::LogCategory;
+
 /// A macro to generate functions to fetch config items
 macro_rules! config_function {
     ($sql:expr, Vec<$_type:ty>, $name:ident) => {
@@ -42,6 +44,53 @@ impl Config {
         Self { pool }
     }
 
+    /// Get the channels the logs of a category are sent to, empty if the category is turned off.
+    /// Categories without channels of their own use the conveyance channels.
+    pub async fn log_channels(&self, category: LogCategory) -> Result<Vec<i64>, ::sqlx::Error> {
+        let enabled = ::sqlx::query!(
+            r#"select enabled from ttc_log_categories where category = $1"#,
+            category.as_str()
+        )
+        .fetch_optional(&*self.pool)
+        .await?
+        .map_or(true, |record| record.enabled);
+        if !enabled {
+            return Ok(Vec::new());
+        }
+
+        let channels = ::sqlx::query!(
+            r#"select channel_id from ttc_log_channels where category = $1 order by id asc"#,
+            category.as_str()
+        )
+        .fetch_all(&*self.pool)
+        .await?
+        .into_iter()
+        .map(|record| record.channel_id)
+        .collect::<Vec<i64>>();
+
+        if channels.is_empty() {
+            self.conveyance_channel().await
+        } else {
+            Ok(channels)
+        }
+    }
+
+    /// Get the channels whose events aren't logged in a category
+    pub async fn log_blacklist_channels(
+        &self,
+        category: LogCategory,
+    ) -> Result<Vec<i64>, ::sqlx::Error> {
+        Ok(::sqlx::query!(
+            r#"select channel_id from ttc_log_blacklist_channels where category = $1"#,
+            category.as_str()
+        )
+        .fetch_all(&*self.pool)
+        .await?
+        .into_iter()
+        .map(|record| record.channel_id)
+        .collect::<Vec<i64>>())
+    }
+
     config_function!(
         r#"select distinct
         tcp.id as config_properties_id,
@@ -80,12 +129,174 @@ impl Config {
     );
     config_function!(
         r#"select distinct
-        tcbc.id as conveyance_blacklist_id,
-        tcbc.channel_id as conveyance_blacklist_channel
+        tcp.id as config_properties_id,
+        tcp.verification_min_account_age as verification_min_account_age
         from ttc_config tc
-        inner join ttc_conveyance_blacklist_channel tcbc on tc.conveyance_blacklist_id  = tcbc.id order by tcbc.id asc"#,
-        Vec<i64>,
-        conveyance_blacklist_channel
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        i64,
+        verification_min_account_age
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.verification_manual_approval as verification_manual_approval
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        bool,
+        verification_manual_approval
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.captcha_enabled as captcha_enabled
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        bool,
+        captcha_enabled
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.captcha_attempts as captcha_attempts
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        i32,
+        captcha_attempts
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.captcha_timeout as captcha_timeout
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        i64,
+        captcha_timeout
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.verification_request_channel as verification_request_channel
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        verification_request_channel
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.onboarding_review_channel as onboarding_review_channel
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        onboarding_review_channel
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.welcome_card_enabled as welcome_card_enabled
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        bool,
+        welcome_card_enabled
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.role_memory_restore_all as role_memory_restore_all
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        bool,
+        role_memory_restore_all
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.message_cache_max_age as message_cache_max_age
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        message_cache_max_age
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.message_cache_max_rows as message_cache_max_rows
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        message_cache_max_rows
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.attachment_archive_enabled as attachment_archive_enabled
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        bool,
+        attachment_archive_enabled
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.attachment_archive_max_size as attachment_archive_max_size
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        i64,
+        attachment_archive_max_size
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.attachment_archive_max_age as attachment_archive_max_age
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        attachment_archive_max_age
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.attachment_archive_budget as attachment_archive_budget
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        attachment_archive_budget
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.impersonation_alert_threshold as impersonation_alert_threshold
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<f64>,
+        impersonation_alert_threshold
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.ghost_ping_window as ghost_ping_window
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        ghost_ping_window
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.support_helper_role as support_helper_role
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        support_helper_role
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.event_max_age as event_max_age
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        event_max_age
     );
     config_function!(
         r#"select distinct
@@ -115,10 +326,55 @@ impl Config {
         welcome_message
     );
     config_function!(
-        r#"select role_id as selfroles, emoji_name
-        from ttc_selfroles"#,
-        Vec<(i64, Option<String>)>,
+        r#"select role_id as selfroles, emoji_name, group_id, required_role_id
+        from ttc_selfroles order by id asc"#,
+        Vec<(i64, Option<String>, Option<i32>, Option<i64>)>,
         selfroles,
-        emoji_name
+        emoji_name,
+        group_id,
+        required_role_id
+    );
+    config_function!(
+        r#"select id as selfrole_groups, "name", description, exclusive
+        from ttc_selfrole_groups order by id asc"#,
+        Vec<(i32, String, Option<String>, bool)>,
+        selfrole_groups,
+        name,
+        description,
+        exclusive
+    );
+    config_function!(
+        r#"select role_id as role_memory_rules, restore
+        from ttc_role_memory_rules"#,
+        Vec<(i64, bool)>,
+        role_memory_rules,
+        restore
+    );
+    config_function!(
+        r#"select role_id as ghost_ping_exempt_roles
+        from ttc_ghost_ping_exempt_roles"#,
+        Vec<i64>,
+        ghost_ping_exempt_roles
+    );
+    config_function!(
+        r#"select channel_id as ghost_ping_disabled_channels
+        from ttc_ghost_ping_disabled_channels"#,
+        Vec<i64>,
+        ghost_ping_disabled_channels
+    );
+    config_function!(
+        r#"select title as onboarding_rules, content
+        from ttc_onboarding_rules order by page_order asc"#,
+        Vec<(String, String)>,
+        onboarding_rules,
+        content
+    );
+    config_function!(
+        r#"select question as onboarding_questions, placeholder, required
+        from ttc_onboarding_questions order by question_order asc limit 5"#,
+        Vec<(String, Option<String>, bool)>,
+        onboarding_questions,
+        placeholder,
+        required
     );
 }
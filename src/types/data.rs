@@ -1,11 +1,22 @@
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
-use poise::serenity_prelude::{ChannelId, Message, RwLock, UserId, Webhook};
+use poise::serenity_prelude::{
+    AuditLogEntryId, ChannelId, Emoji, EmojiId, GuildId, Message, RwLock, UserId, Webhook,
+};
 use sqlx::PgPool;
 
 use crate::{
+    events::invites::CachedInvite,
     types::{colors::Colors, config::Config},
-    utils::bee_utils::{BeeifiedUser, BeezoneChannel},
+    utils::{
+        bee_utils::{BeeifiedUser, BeezoneChannel},
+        captcha::PendingCaptcha,
+        event_log::EventLog,
+    },
 };
 
 pub struct Data {
@@ -13,6 +24,14 @@ pub struct Data {
     pub beeified_users: RwLock<HashMap<UserId, BeeifiedUser>>,
     pub beezone_channels: RwLock<HashMap<ChannelId, BeezoneChannel>>,
     pub webhooks: RwLock<HashMap<ChannelId, Webhook>>,
+    pub captchas: RwLock<HashMap<UserId, PendingCaptcha>>,
+    pub onboarding_users: RwLock<HashSet<UserId>>,
+    pub emoji_snapshots: RwLock<HashMap<GuildId, HashMap<EmojiId, Emoji>>>,
+    pub invites: RwLock<HashMap<GuildId, HashMap<String, CachedInvite>>>,
+    pub audit_log_claims: RwLock<HashMap<AuditLogEntryId, u64>>,
+    pub selfrole_refreshes: RwLock<HashSet<GuildId>>,
+    pub ghost_ping_notices: RwLock<HashMap<(ChannelId, UserId), Instant>>,
+    pub event_log: Option<EventLog>,
     pub pool: Arc<PgPool>,
     pub thread_name_regex: regex::Regex,
     pub startup_time: Instant,
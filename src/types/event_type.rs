@@ -0,0 +1,65 @@
+/// The kinds of events kept in the event store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum EventType {
+    #[name = "Message deleted"]
+    MessageDelete,
+    #[name = "Message edited"]
+    MessageEdit,
+    #[name = "Joined"]
+    MemberJoin,
+    #[name = "Left"]
+    MemberLeave,
+    #[name = "Banned"]
+    Ban,
+    #[name = "Unbanned"]
+    Unban,
+    #[name = "Member updated"]
+    MemberUpdate,
+    #[name = "Profile updated"]
+    ProfileUpdate,
+}
+
+impl EventType {
+    /// The name of the event type as it is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::MessageDelete => "message_delete",
+            EventType::MessageEdit => "message_edit",
+            EventType::MemberJoin => "member_join",
+            EventType::MemberLeave => "member_leave",
+            EventType::Ban => "ban",
+            EventType::Unban => "unban",
+            EventType::MemberUpdate => "member_update",
+            EventType::ProfileUpdate => "profile_update",
+        }
+    }
+
+    /// Get the event type back from the name stored in the database
+    pub fn from_db(name: &str) -> Option<EventType> {
+        match name {
+            "message_delete" => Some(EventType::MessageDelete),
+            "message_edit" => Some(EventType::MessageEdit),
+            "member_join" => Some(EventType::MemberJoin),
+            "member_leave" => Some(EventType::MemberLeave),
+            "ban" => Some(EventType::Ban),
+            "unban" => Some(EventType::Unban),
+            "member_update" => Some(EventType::MemberUpdate),
+            "profile_update" => Some(EventType::ProfileUpdate),
+            _ => None,
+        }
+    }
+
+    /// A readable name for the event type
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventType::MessageDelete => "Message deleted",
+            EventType::MessageEdit => "Message edited",
+            EventType::MemberJoin => "Joined",
+            EventType::MemberLeave => "Left",
+            EventType::Ban => "Banned",
+            EventType::Unban => "Unbanned",
+            EventType::MemberUpdate => "Member updated",
+            EventType::ProfileUpdate => "Profile updated",
+        }
+    }
+}
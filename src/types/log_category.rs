@@ -0,0 +1,28 @@
+/// The categories logs are sorted into, each can be routed to its own channels and turned off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogCategory {
+    Messages,
+    MemberJoinLeave,
+    MemberUpdates,
+    Bans,
+    Moderation,
+    Automod,
+    Voice,
+    ServerUpdates,
+}
+
+impl LogCategory {
+    /// The name of the category as it is stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogCategory::Messages => "messages",
+            LogCategory::MemberJoinLeave => "member_join_leave",
+            LogCategory::MemberUpdates => "member_updates",
+            LogCategory::Bans => "bans",
+            LogCategory::Moderation => "moderation",
+            LogCategory::Automod => "automod",
+            LogCategory::Voice => "voice",
+            LogCategory::ServerUpdates => "server_updates",
+        }
+    }
+}
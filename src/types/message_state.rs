@@ -0,0 +1,8 @@
+/// What happened to a cached message after it was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum MessageState {
+    #[name = "Deleted"]
+    Deleted,
+    #[name = "Edited"]
+    Edited,
+}
@@ -0,0 +1,247 @@
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::Utc;
+use poise::serenity_prelude::{Attachment, Context, GuildId, MessageId, PremiumTier};
+use sqlx::PgPool;
+
+use crate::{types::config::Config, Error};
+
+const ATTACHMENT_ARCHIVE: &str = "attachment-archive";
+// How often old attachments are removed from the archive
+const PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+// The most Discord accepts in files on a single message, raised by the boost tier of the guild
+const UPLOAD_LIMIT: u64 = 25 * 1024 * 1024;
+const UPLOAD_LIMIT_TIER_2: u64 = 50 * 1024 * 1024;
+const UPLOAD_LIMIT_TIER_3: u64 = 100 * 1024 * 1024;
+const MAX_FILES_PER_MESSAGE: usize = 10;
+
+/// An archived attachment read back from the disk, ready to be uploaded again
+pub struct ArchivedAttachment {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+/// Get the path to the attachment archive directory
+fn get_archive_path() -> Result<PathBuf, Error> {
+    let mut path = current_dir()?;
+    path.push(ATTACHMENT_ARCHIVE);
+    Ok(path)
+}
+
+/// Replace everything but a few safe characters, so the name can't leave the archive directory
+fn sanitize_filename(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Get how many bytes of files can be uploaded on a single message in a guild, falling back to the
+/// limit of unboosted guilds when the guild isn't cached
+pub fn upload_limit(ctx: &Context, guild_id: Option<GuildId>) -> u64 {
+    match guild_id.and_then(|guild_id| ctx.cache.guild_field(guild_id, |guild| guild.premium_tier))
+    {
+        Some(PremiumTier::Tier2) => UPLOAD_LIMIT_TIER_2,
+        Some(PremiumTier::Tier3) => UPLOAD_LIMIT_TIER_3,
+        _ => UPLOAD_LIMIT,
+    }
+}
+
+/// Download the attachments of a message that are small enough into the archive
+pub async fn archive_attachments(
+    pool: Arc<PgPool>,
+    message_id: MessageId,
+    attachments: Vec<Attachment>,
+) {
+    if let Err(why) = try_archive_attachments(&pool, message_id, &attachments).await {
+        log::error!(
+            "Unable to archive the attachments of message {}: {}",
+            message_id,
+            why
+        );
+    }
+}
+
+async fn try_archive_attachments(
+    pool: &Arc<PgPool>,
+    message_id: MessageId,
+    attachments: &[Attachment],
+) -> Result<(), Error> {
+    let config = Config::new(Arc::clone(pool));
+    if !config.attachment_archive_enabled().await? {
+        return Ok(());
+    }
+    let max_size = config.attachment_archive_max_size().await?;
+
+    let archive_path = get_archive_path()?;
+    fs::create_dir_all(&archive_path)?;
+
+    for attachment in attachments {
+        if attachment.size > max_size as u64 {
+            log::debug!(
+                "Not archiving attachment {}, it is too large ({} bytes)",
+                attachment.id,
+                attachment.size
+            );
+            continue;
+        }
+
+        let data = attachment.download().await?;
+        let mut path = archive_path.clone();
+        path.push(format!(
+            "{}-{}-{}",
+            message_id.0,
+            attachment.id.0,
+            sanitize_filename(&attachment.filename)
+        ));
+        fs::write(&path, &data)?;
+
+        sqlx::query!(
+            r#"INSERT INTO ttc_attachment_archive (message_id, filename, "path", "size", archived_at) VALUES($1, $2, $3, $4, $5)"#,
+            message_id.0 as i64,
+            attachment.filename,
+            path.to_string_lossy().to_string(),
+            data.len() as i64,
+            Utc::now()
+        )
+        .execute(&**pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Read the archived attachments of deleted messages back from the disk. Only as many files as
+/// Discord accepts on a single message are read, the names of the rest are returned separately.
+pub async fn load_archived_attachments(
+    pool: &PgPool,
+    message_ids: &[i64],
+    max_files: usize,
+    upload_limit: u64,
+) -> Result<(Vec<ArchivedAttachment>, Vec<String>), Error> {
+    let records = sqlx::query!(
+        r#"SELECT filename, "path", "size" FROM ttc_attachment_archive WHERE message_id = ANY($1) ORDER BY id"#,
+        message_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let max_files = max_files.min(MAX_FILES_PER_MESSAGE);
+    let mut loaded = Vec::new();
+    let mut skipped = Vec::new();
+    let mut total_size = 0;
+    for record in records {
+        if loaded.len() >= max_files || total_size + record.size as u64 > upload_limit {
+            skipped.push(record.filename);
+            continue;
+        }
+        match fs::read(&record.path) {
+            Ok(data) => {
+                total_size += data.len() as u64;
+                loaded.push(ArchivedAttachment {
+                    filename: record.filename,
+                    data,
+                });
+            }
+            Err(why) => {
+                log::warn!(
+                    "Unable to read archived attachment {}: {}",
+                    record.path,
+                    why
+                );
+                skipped.push(record.filename);
+            }
+        }
+    }
+
+    Ok((loaded, skipped))
+}
+
+/// Remove the archived attachments of messages, used once they have been logged. Returns how many
+/// attachments were removed.
+pub async fn remove_archived_attachments(pool: &PgPool, message_ids: &[i64]) -> Result<u64, Error> {
+    let records = sqlx::query!(
+        r#"DELETE FROM ttc_attachment_archive WHERE message_id = ANY($1) RETURNING "path""#,
+        message_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for record in &records {
+        remove_file(&record.path);
+    }
+
+    Ok(records.len() as u64)
+}
+
+fn remove_file(path: &str) {
+    // The file may already be gone, which is fine since the goal is to get rid of it
+    if Path::new(path).exists() {
+        if let Err(why) = fs::remove_file(path) {
+            log::warn!("Unable to remove archived attachment {}: {}", path, why);
+        }
+    }
+}
+
+/// Remove the attachments older than the configured maximum age, then the oldest ones until the
+/// archive fits into the configured disk budget. Returns how many attachments were removed.
+async fn prune_attachment_archive(pool: &PgPool, config: &Config) -> Result<usize, Error> {
+    let mut removed = 0;
+
+    if let Some(max_age) = config.attachment_archive_max_age().await? {
+        let records = sqlx::query!(
+            r#"DELETE FROM ttc_attachment_archive WHERE archived_at < $1 RETURNING "path""#,
+            Utc::now() - chrono::Duration::seconds(max_age)
+        )
+        .fetch_all(pool)
+        .await?;
+        for record in &records {
+            remove_file(&record.path);
+        }
+        removed += records.len();
+    }
+
+    if let Some(budget) = config.attachment_archive_budget().await? {
+        // Keep the newest attachments that fit into the budget
+        let records = sqlx::query!(
+            r#"DELETE FROM ttc_attachment_archive WHERE id IN
+            (SELECT id FROM (SELECT id, (SUM("size") OVER (ORDER BY archived_at DESC, id DESC))::int8 AS total
+            FROM ttc_attachment_archive) AS totals WHERE total > $1)
+            RETURNING "path""#,
+            budget
+        )
+        .fetch_all(pool)
+        .await?;
+        for record in &records {
+            remove_file(&record.path);
+        }
+        removed += records.len();
+    }
+
+    Ok(removed)
+}
+
+/// Periodically prune the attachment archive
+pub async fn attachment_archive_prune_task(pool: Arc<PgPool>) {
+    let config = Config::new(Arc::clone(&pool));
+    loop {
+        match prune_attachment_archive(&pool, &config).await {
+            Ok(0) => (),
+            Ok(removed) => log::info!("Removed {} attachments from the archive", removed),
+            Err(why) => log::error!("Unable to prune the attachment archive: {}", why),
+        }
+        tokio::time::sleep(PRUNE_INTERVAL).await;
+    }
+}
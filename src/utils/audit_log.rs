@@ -0,0 +1,183 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
+use poise::serenity_prelude::{
+    Action, AuditLogEntry, AuditLogEntryId, ChannelId, Context, CreateEmbed, GuildId, Message, User,
+};
+
+use crate::types::data::Data;
+
+// The audit log lags behind the gateway events, so it is checked a few times with growing delays.
+// Authors deleting their own messages never show up in it, so those deletions wait for every retry.
+const RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(2),
+    Duration::from_secs(3),
+    Duration::from_secs(6),
+];
+// How old in seconds a new audit log entry can be and still belong to the event being handled
+const ENTRY_MAX_AGE: i64 = 30;
+// How long in seconds the claimed entries are remembered, Discord only merges recent actions
+const CLAIM_MAX_AGE: i64 = 3600;
+const ENTRY_FETCH_LIMIT: u8 = 25;
+
+/// The moderator responsible for an event, according to the audit log
+pub struct Attribution {
+    pub moderator: User,
+    pub reason: Option<String>,
+}
+
+/// Decide whether an audit log entry belongs to the event being handled. Discord merges repeated
+/// actions into one entry and counts them, so an entry can be claimed as many times as its count.
+/// Entries that are too old to be new only have their count recorded, so later merges are noticed.
+fn claim_entry(
+    claims: &mut HashMap<AuditLogEntryId, u64>,
+    id: AuditLogEntryId,
+    count: u64,
+    recent: bool,
+) -> bool {
+    match claims.get_mut(&id) {
+        Some(claimed) if *claimed < count => {
+            *claimed += 1;
+            true
+        }
+        Some(_) => false,
+        None if recent => {
+            claims.insert(id, 1);
+            true
+        }
+        None => {
+            claims.insert(id, count);
+            false
+        }
+    }
+}
+
+/// Check whether an audit log entry is about the target of the event being handled
+fn is_about_target(entry: &AuditLogEntry, target_id: u64, channel_id: Option<ChannelId>) -> bool {
+    if entry.target_id != Some(target_id) {
+        return false;
+    }
+    match channel_id {
+        Some(channel_id) => entry.options.as_ref().and_then(|o| o.channel_id) == Some(channel_id),
+        None => true,
+    }
+}
+
+/// Look for the audit log entry of an event and return who caused it. Returns `None` if the
+/// target did it themselves, the audit log isn't accessible or no entry showed up in time.
+pub async fn find_attribution(
+    ctx: &Context,
+    data: &Data,
+    guild_id: GuildId,
+    action: Action,
+    target_id: u64,
+    channel_id: Option<ChannelId>,
+) -> Option<Attribution> {
+    for delay in RETRY_DELAYS {
+        tokio::time::sleep(delay).await;
+
+        let logs = match guild_id
+            .audit_logs(ctx, Some(action.num()), None, None, Some(ENTRY_FETCH_LIMIT))
+            .await
+        {
+            Ok(logs) => logs,
+            Err(why) => {
+                log::warn!("Unable to read the audit log: {}", why);
+                return None;
+            }
+        };
+
+        // Find the entry while holding the lock, so no other event can claim it at the same time
+        let entry = {
+            let now = Utc::now().timestamp();
+            let mut claims = data.audit_log_claims.write().await;
+            claims.retain(|id, _| now - id.created_at().unix_timestamp() < CLAIM_MAX_AGE);
+
+            // Entries are sorted newest first
+            logs.entries.iter().find(|entry| {
+                if !is_about_target(entry, target_id, channel_id) {
+                    return false;
+                }
+
+                let count = entry.options.as_ref().and_then(|o| o.count).unwrap_or(1);
+                let recent = now - entry.id.created_at().unix_timestamp() <= ENTRY_MAX_AGE;
+                claim_entry(&mut claims, entry.id, count, recent)
+            })
+        };
+
+        if let Some(entry) = entry {
+            // Changes the target made to themselves aren't moderation, there is nothing left to find
+            if entry.user_id.0 == target_id {
+                return None;
+            }
+            let moderator = match logs.users.get(&entry.user_id) {
+                Some(user) => user.clone(),
+                None => match entry.user_id.to_user(ctx).await {
+                    Ok(user) => user,
+                    Err(why) => {
+                        log::warn!("Error getting user based on user id: {}", why);
+                        return None;
+                    }
+                },
+            };
+            return Some(Attribution {
+                moderator,
+                reason: entry.reason.clone(),
+            });
+        }
+    }
+
+    None
+}
+
+/// Add the responsible moderator to logs that have already been sent, editing them instead of
+/// sending the logs again
+pub async fn add_attribution(ctx: &Context, messages: Vec<Message>, attribution: &Attribution) {
+    for mut message in messages {
+        let mut embed = match message.embeds.first() {
+            Some(embed) => CreateEmbed::from(embed.clone()),
+            None => continue,
+        };
+        embed
+            .field(
+                "Responsible moderator",
+                format!(
+                    "<@{}> ({})",
+                    attribution.moderator.id,
+                    attribution.moderator.tag()
+                ),
+                false,
+            )
+            .field(
+                "Reason",
+                attribution.reason.as_deref().unwrap_or("No reason given"),
+                false,
+            );
+
+        if let Err(why) = message.edit(ctx, |m| m.set_embed(embed)).await {
+            log::error!("Unable to add the responsible moderator to a log: {}", why);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_log_claims() {
+        let mut claims = HashMap::new();
+        let id = AuditLogEntryId(1);
+
+        // A new entry belongs to the event, but only once
+        assert!(claim_entry(&mut claims, id, 1, true));
+        assert!(!claim_entry(&mut claims, id, 1, true));
+        // Discord merged another action into the entry
+        assert!(claim_entry(&mut claims, id, 2, true));
+
+        // Entries seen for the first time when they're old only start being tracked
+        let old_id = AuditLogEntryId(2);
+        assert!(!claim_entry(&mut claims, old_id, 3, false));
+        assert!(claim_entry(&mut claims, old_id, 4, false));
+    }
+}
@@ -0,0 +1,157 @@
+use poise::serenity_prelude::{Timestamp, UserId};
+use rand::Rng;
+use ril::prelude::*;
+use std::fs;
+
+use crate::{utils::userinfo::get_basepath, Error};
+
+// Characters that are hard to confuse with each other, even when distorted
+const CAPTCHA_CHARSET: &[u8] = b"ABCDEFGHJKLMNPRSTUVWXYZ23456789";
+const CAPTCHA_LENGTH: usize = 6;
+const CAPTCHA_WIDTH: u32 = 320;
+const CAPTCHA_HEIGHT: u32 = 110;
+const CAPTCHA_FONT_SIZE: f32 = 48.0;
+const CAPTCHA_NOISE_LINES: u32 = 6;
+const CAPTCHA_NOISE_PIXELS: u32 = 1200;
+
+#[derive(Debug, Clone)]
+pub struct PendingCaptcha {
+    pub answer: String,
+    pub attempts_left: i32,
+    pub expires_at: Timestamp,
+}
+
+impl PendingCaptcha {
+    pub fn new(answer: String, attempts_left: i32, expires_at: Timestamp) -> Self {
+        Self {
+            answer,
+            attempts_left,
+            expires_at,
+        }
+    }
+
+    /// Check whether the captcha can no longer be answered, or counts as a failed one
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Timestamp::now()
+    }
+
+    /// Compare the answer of the user with the expected one, ignoring case and whitespace
+    pub fn matches(&self, answer: &str) -> bool {
+        let answer: String = answer
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase();
+        answer == self.answer
+    }
+}
+
+/// Generate a random captcha text
+pub fn generate_captcha_text() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CAPTCHA_LENGTH)
+        .map(|_| CAPTCHA_CHARSET[rng.gen_range(0..CAPTCHA_CHARSET.len())] as char)
+        .collect()
+}
+
+/// Get the file name of the captcha image of a user
+pub fn get_captcha_filename(user_id: UserId) -> String {
+    format!("captcha-{}.png", user_id.0)
+}
+
+/// Get the path the captcha image of a user is saved at
+pub fn get_captcha_path(user_id: UserId) -> Result<String, Error> {
+    let mut path = get_basepath()?;
+    path.push(get_captcha_filename(user_id));
+    Ok(path.to_str().unwrap().to_string())
+}
+
+/// Remove a captcha image after it has been sent
+pub fn remove_captcha(path: &str) {
+    if let Err(why) = fs::remove_file(path) {
+        log::warn!("Unable to remove captcha image {}: {}", path, why);
+    }
+}
+
+/// Render the captcha text as a distorted image and save it, returns the path of the image
+pub fn render_captcha(text: &str, user_id: UserId) -> Result<String, Error> {
+    let mut rng = rand::thread_rng();
+    let background = Rgba::new(32, 34, 37, 255);
+    let mut cvs = Image::new(CAPTCHA_WIDTH, CAPTCHA_HEIGHT, background);
+
+    let font = match Font::from_bytes(
+        include_bytes!("../../res/DejaVuSans.ttf"),
+        CAPTCHA_FONT_SIZE,
+    ) {
+        Ok(font) => font,
+        Err(_) => return Err(Error::from("Font could not be loaded for the captcha")),
+    };
+
+    // Draw every character on its own with a random color and offset
+    let spacing = (CAPTCHA_WIDTH - 40) / text.len() as u32;
+    for (i, c) in text.chars().enumerate() {
+        let color = Rgba::new(
+            rng.gen_range(150..=255),
+            rng.gen_range(150..=255),
+            rng.gen_range(150..=255),
+            255,
+        );
+        let x = 20 + i as u32 * spacing + rng.gen_range(0..8);
+        let y = rng.gen_range(10..CAPTCHA_HEIGHT - CAPTCHA_FONT_SIZE as u32 - 10);
+        let segment = TextSegment::new(&font, &c.to_string(), color).with_position(x, y);
+        cvs.draw(&segment);
+    }
+
+    // Bend the text along a random sine wave
+    let amplitude = rng.gen_range(4.0..8.0);
+    let period = rng.gen_range(30.0..50.0);
+    let phase = rng.gen_range(0.0..std::f64::consts::TAU);
+    let mut distorted = Image::new(CAPTCHA_WIDTH, CAPTCHA_HEIGHT, background);
+    for x in 0..CAPTCHA_WIDTH {
+        let shift = (amplitude * (x as f64 / period + phase).sin()) as i64;
+        for y in 0..CAPTCHA_HEIGHT {
+            let source_y = y as i64 + shift;
+            if source_y >= 0 && source_y < CAPTCHA_HEIGHT as i64 {
+                distorted.set_pixel(x, y, *cvs.pixel(x, source_y as u32));
+            }
+        }
+    }
+
+    // Cross the text with some lines
+    for _ in 0..CAPTCHA_NOISE_LINES {
+        let color = Rgba::new(
+            rng.gen_range(100..=255),
+            rng.gen_range(100..=255),
+            rng.gen_range(100..=255),
+            255,
+        );
+        let (x0, y0) = (0.0, rng.gen_range(0.0..CAPTCHA_HEIGHT as f64));
+        let (x1, y1) = (
+            CAPTCHA_WIDTH as f64 - 1.0,
+            rng.gen_range(0.0..CAPTCHA_HEIGHT as f64),
+        );
+        for step in 0..CAPTCHA_WIDTH {
+            let t = step as f64 / CAPTCHA_WIDTH as f64;
+            let x = (x0 + (x1 - x0) * t) as u32;
+            let y = (y0 + (y1 - y0) * t) as u32;
+            distorted.set_pixel(x, y.min(CAPTCHA_HEIGHT - 1), color);
+        }
+    }
+
+    // And finally sprinkle some noise on top
+    for _ in 0..CAPTCHA_NOISE_PIXELS {
+        let value = rng.gen_range(0..=255);
+        distorted.set_pixel(
+            rng.gen_range(0..CAPTCHA_WIDTH),
+            rng.gen_range(0..CAPTCHA_HEIGHT),
+            Rgba::new(value, value, value, 255),
+        );
+    }
+
+    fs::create_dir_all(get_basepath()?)?;
+    let path = get_captcha_path(user_id)?;
+    match distorted.save_inferred(path.as_str()) {
+        Ok(_) => Ok(path),
+        Err(why) => Err(format!("Couldn't save captcha to filesystem: {}", why).into()),
+    }
+}
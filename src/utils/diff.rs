@@ -0,0 +1,206 @@
+// Above this many token pairs the changed middle part is shown as replaced instead of diffed, to
+// keep the lookup table of very long messages small
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// A part of a word diff
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffSpan<'a> {
+    Equal(&'a str),
+    Deleted(&'a str),
+    Inserted(&'a str),
+}
+
+/// Split text into words and the whitespace between them, joining the tokens gives the text back
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut last_whitespace = None;
+    for (index, c) in text.char_indices() {
+        let whitespace = c.is_whitespace();
+        if last_whitespace.is_some() && last_whitespace != Some(whitespace) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        last_whitespace = Some(whitespace);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Compare two texts word by word using the longest common subsequence of their words
+pub fn word_diff<'a>(old: &'a str, new: &'a str) -> Vec<DiffSpan<'a>> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+
+    // Most edits only touch a small part of the message, so the common start and end are skipped
+    let prefix = old_tokens
+        .iter()
+        .zip(&new_tokens)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_tokens[prefix..]
+        .iter()
+        .rev()
+        .zip(new_tokens[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_tokens[prefix..old_tokens.len() - suffix];
+    let new_middle = &new_tokens[prefix..new_tokens.len() - suffix];
+
+    let mut spans: Vec<DiffSpan> = old_tokens[..prefix]
+        .iter()
+        .map(|token| DiffSpan::Equal(token))
+        .collect();
+
+    if (old_middle.len() + 1) * (new_middle.len() + 1) > MAX_DIFF_CELLS {
+        spans.extend(old_middle.iter().map(|token| DiffSpan::Deleted(token)));
+        spans.extend(new_middle.iter().map(|token| DiffSpan::Inserted(token)));
+    } else {
+        // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+        let mut lengths = vec![vec![0u32; new_middle.len() + 1]; old_middle.len() + 1];
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                lengths[i][j] = if old_middle[i] == new_middle[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_middle.len() && j < new_middle.len() {
+            if old_middle[i] == new_middle[j] {
+                spans.push(DiffSpan::Equal(old_middle[i]));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                spans.push(DiffSpan::Deleted(old_middle[i]));
+                i += 1;
+            } else {
+                spans.push(DiffSpan::Inserted(new_middle[j]));
+                j += 1;
+            }
+        }
+        spans.extend(old_middle[i..].iter().map(|token| DiffSpan::Deleted(token)));
+        spans.extend(
+            new_middle[j..]
+                .iter()
+                .map(|token| DiffSpan::Inserted(token)),
+        );
+    }
+
+    spans.extend(
+        old_tokens[old_tokens.len() - suffix..]
+            .iter()
+            .map(|token| DiffSpan::Equal(token)),
+    );
+    spans
+}
+
+/// Escape the characters that would otherwise be read as markdown by Discord. Underscores are left
+/// alone, since escaping them would break custom emojis.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '~' | '`' | '|' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Wrap a changed part in markdown, keeping the whitespace around it outside of the markers so
+/// Discord still renders them
+fn mark(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let start = text.len() - text.trim_start().len();
+    let end = start + trimmed.len();
+    format!(
+        "{}{}{}{}{}",
+        &text[..start],
+        marker,
+        escape_markdown(trimmed),
+        marker,
+        &text[end..]
+    )
+}
+
+/// Render a word diff with Discord markdown, `~~deleted~~` and `**inserted**`
+pub fn render_diff(spans: &[DiffSpan]) -> String {
+    let mut rendered = String::new();
+    // Consecutive parts of the same kind are marked together, so the markers don't pile up
+    let mut index = 0;
+    while index < spans.len() {
+        let mut text = String::new();
+        let kind = std::mem::discriminant(&spans[index]);
+        while index < spans.len() && std::mem::discriminant(&spans[index]) == kind {
+            text.push_str(match spans[index] {
+                DiffSpan::Equal(token) | DiffSpan::Deleted(token) | DiffSpan::Inserted(token) => {
+                    token
+                }
+            });
+            index += 1;
+        }
+        match spans[index - 1] {
+            DiffSpan::Equal(_) => rendered.push_str(&escape_markdown(&text)),
+            DiffSpan::Deleted(_) => {
+                // Deleted whitespace would only show up as markers without any text in between
+                if !text.trim().is_empty() {
+                    rendered.push_str(&mark(&text, "~~"));
+                }
+            }
+            DiffSpan::Inserted(_) => rendered.push_str(&mark(&text, "**")),
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("hello  world\n!"),
+            vec!["hello", "  ", "world", "\n", "!"]
+        );
+        assert_eq!(tokenize(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn diff() {
+        assert_eq!(
+            word_diff("the quick fox", "the slow fox"),
+            vec![
+                DiffSpan::Equal("the"),
+                DiffSpan::Equal(" "),
+                DiffSpan::Deleted("quick"),
+                DiffSpan::Inserted("slow"),
+                DiffSpan::Equal(" "),
+                DiffSpan::Equal("fox"),
+            ]
+        );
+        assert_eq!(
+            render_diff(&word_diff("the quick fox", "the slow fox")),
+            "the ~~quick~~**slow** fox"
+        );
+        assert_eq!(render_diff(&word_diff("a b c", "a c d")), "a ~~b~~ c **d**");
+        assert_eq!(render_diff(&word_diff("same", "same")), "same");
+    }
+
+    #[test]
+    fn diff_escapes_markdown() {
+        assert_eq!(
+            render_diff(&word_diff("use *this*", "use ~~that~~")),
+            "use ~~\\*this\\*~~**\\~\\~that\\~\\~**"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::Value;
+
+use crate::Error;
+
+const CURRENT_FILE: &str = "events.jsonl";
+// How many events can wait to be written before new ones are dropped
+const QUEUE_SIZE: usize = 10_000;
+const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Settings of the event log, read from the `event_log` section of the core config
+pub struct EventLogConfig {
+    pub directory: PathBuf,
+    // 0 turns off rotating by size
+    pub max_size: u64,
+    pub rotate_daily: bool,
+    pub compress: bool,
+}
+
+impl EventLogConfig {
+    /// Read the settings from the core config, returns `None` if the event log isn't configured
+    pub fn from_yaml(config: &serde_yaml::Value) -> Option<EventLogConfig> {
+        let directory = config["directory"].as_str()?;
+        Some(EventLogConfig {
+            directory: PathBuf::from(directory),
+            max_size: config["max_size"].as_u64().unwrap_or(DEFAULT_MAX_SIZE),
+            rotate_daily: config["rotate_daily"].as_bool().unwrap_or(true),
+            compress: config["compress"].as_bool().unwrap_or(true),
+        })
+    }
+}
+
+/// Handle for writing events into the event log as JSON lines. The writing happens on a thread of
+/// its own, so slow disks never hold up the event handlers.
+pub struct EventLog {
+    sender: SyncSender<String>,
+}
+
+impl EventLog {
+    /// Open the event log and start the thread writing it
+    pub fn start(config: EventLogConfig) -> Result<EventLog, Error> {
+        fs::create_dir_all(&config.directory)?;
+        let writer = RotatingWriter::open(config)?;
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+        thread::Builder::new()
+            .name("event-log".to_string())
+            .spawn(move || writer.run(receiver))?;
+        Ok(EventLog { sender })
+    }
+
+    /// Queue an event to be written without waiting, events are dropped if the queue is full
+    pub fn write(&self, event: Value) {
+        match self.sender.try_send(event.to_string()) {
+            Ok(_) => (),
+            Err(TrySendError::Full(_)) => {
+                log::warn!("The event log is falling behind, dropping an event")
+            }
+            Err(TrySendError::Disconnected(_)) => log::error!("The event log writer has stopped"),
+        }
+    }
+}
+
+/// Check whether the current file has to be rotated before a line can be written to it
+fn needs_rotation(
+    size: u64,
+    line_length: u64,
+    max_size: u64,
+    opened_on: NaiveDate,
+    today: NaiveDate,
+    rotate_daily: bool,
+) -> bool {
+    // An empty file is never rotated, even if a single line is larger than the maximum size
+    let too_large = max_size > 0 && size > 0 && size + line_length > max_size;
+    let new_day = rotate_daily && opened_on != today;
+    too_large || (new_day && size > 0)
+}
+
+struct RotatingWriter {
+    config: EventLogConfig,
+    file: BufWriter<File>,
+    size: u64,
+    opened_on: NaiveDate,
+}
+
+impl RotatingWriter {
+    /// Continue the current file if there is one, it is rotated on the first write if it's old
+    fn open(config: EventLogConfig) -> io::Result<RotatingWriter> {
+        let path = config.directory.join(CURRENT_FILE);
+        let (size, opened_on) = match fs::metadata(&path) {
+            Ok(metadata) => (
+                metadata.len(),
+                DateTime::<Utc>::from(metadata.modified()?).date_naive(),
+            ),
+            Err(_) => (0, Utc::now().date_naive()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(RotatingWriter {
+            config,
+            file: BufWriter::new(file),
+            size,
+            opened_on,
+        })
+    }
+
+    /// Write the queued events until the bot shuts down
+    fn run(mut self, receiver: Receiver<String>) {
+        while let Ok(line) = receiver.recv() {
+            self.write_line(&line);
+            // Write out the events that piled up in the meantime before flushing
+            while let Ok(line) = receiver.try_recv() {
+                self.write_line(&line);
+            }
+            if let Err(why) = self.file.flush() {
+                log::error!("Unable to write the event log: {}", why);
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        let line_length = line.len() as u64 + 1;
+        let today = Utc::now().date_naive();
+        // An empty file belongs to the day its first line is written on
+        if self.size == 0 {
+            self.opened_on = today;
+        }
+        if needs_rotation(
+            self.size,
+            line_length,
+            self.config.max_size,
+            self.opened_on,
+            today,
+            self.config.rotate_daily,
+        ) {
+            if let Err(why) = self.rotate() {
+                log::error!("Unable to rotate the event log: {}", why);
+            }
+        }
+
+        match writeln!(self.file, "{}", line) {
+            Ok(_) => self.size += line_length,
+            Err(why) => log::error!("Unable to write the event log: {}", why),
+        }
+    }
+
+    /// Move the current file aside and start a new one, compressing the old one if configured
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let current = self.config.directory.join(CURRENT_FILE);
+        let rotated = self.config.directory.join(format!(
+            "events-{}.jsonl",
+            Utc::now().format("%Y%m%d-%H%M%S%.3f")
+        ));
+        fs::rename(&current, &rotated)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&current)?;
+        self.file = BufWriter::new(file);
+        self.size = 0;
+        self.opened_on = Utc::now().date_naive();
+
+        // Compressing a large file takes a while, so it happens next to the writing
+        if self.config.compress {
+            thread::spawn(move || {
+                if let Err(why) = compress_file(&rotated) {
+                    log::error!("Unable to compress {}: {}", rotated.display(), why);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Gzip a file next to itself and remove the original
+fn compress_file(path: &Path) -> io::Result<()> {
+    let mut compressed_path = path.as_os_str().to_owned();
+    compressed_path.push(".gz");
+
+    let mut input = File::open(path)?;
+    let output = BufWriter::new(File::create(&compressed_path)?);
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.flush()?;
+
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation() {
+        let today = NaiveDate::from_ymd_opt(2023, 5, 2).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+
+        assert!(!needs_rotation(90, 10, 100, today, today, true));
+        assert!(needs_rotation(91, 10, 100, today, today, true));
+        // A size of 0 turns the size limit off and empty files are never rotated
+        assert!(!needs_rotation(1000, 10, 0, today, today, true));
+        assert!(!needs_rotation(0, 1000, 100, today, today, true));
+
+        assert!(needs_rotation(10, 10, 100, yesterday, today, true));
+        assert!(!needs_rotation(10, 10, 100, yesterday, today, false));
+        assert!(!needs_rotation(0, 10, 100, yesterday, today, true));
+    }
+}
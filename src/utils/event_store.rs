@@ -0,0 +1,425 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{GuildId, UserId};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{
+    types::{config::Config, data::Data, event_type::EventType},
+    utils::{audit_log::Attribution, helper_functions::shorten},
+    Error,
+};
+
+// How many characters of message contents are shown for a single event in the history
+const HISTORY_CONTENT_LENGTH: usize = 200;
+// How often events older than the configured maximum age are removed
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// An event read back from the event store
+pub struct StoredEvent {
+    pub event_type: String,
+    pub event_time: DateTime<Utc>,
+    pub payload: Value,
+}
+
+/// Write an event into the event store and the event log if one is configured, returning its id
+/// so it can be amended later. Failing to store an event is only logged, so it never stops the
+/// logs from being sent.
+pub async fn record_event(
+    data: &Data,
+    guild_id: Option<GuildId>,
+    user_id: UserId,
+    event_type: EventType,
+    payload: Value,
+) -> Option<i64> {
+    let event_time = Utc::now();
+    // The payload is passed as text and cast in the query, which keeps sqlx away from json types
+    let event_id = match sqlx::query!(
+        r#"INSERT INTO ttc_events (guild_id, user_id, event_type, event_time, payload) VALUES($1, $2, $3, $4, $5::text::jsonb) RETURNING id"#,
+        guild_id.map(|id| id.0 as i64),
+        user_id.0 as i64,
+        event_type.as_str(),
+        event_time,
+        payload.to_string()
+    )
+    .fetch_one(&*data.pool)
+    .await
+    {
+        Ok(record) => Some(record.id),
+        Err(why) => {
+            log::error!("Unable to store {} event: {}", event_type.as_str(), why);
+            None
+        }
+    };
+
+    if let Some(event_log) = &data.event_log {
+        event_log.write(json!({
+            "event_id": event_id,
+            "time": event_time.to_rfc3339(),
+            "guild_id": guild_id.map(|id| id.0.to_string()),
+            "user_id": user_id.0.to_string(),
+            "type": event_type.as_str(),
+            "payload": payload,
+        }));
+    }
+
+    event_id
+}
+
+/// Add the responsible moderator to a stored event once the audit log has been checked, the event
+/// log gets it as an event of its own since its lines can't be changed
+pub async fn add_event_attribution(data: &Data, event_id: i64, attribution: &Attribution) {
+    let attribution = json!({
+        "moderator_id": attribution.moderator.id.0.to_string(),
+        "reason": attribution.reason,
+    });
+
+    if let Some(event_log) = &data.event_log {
+        event_log.write(json!({
+            "event_id": event_id,
+            "time": Utc::now().to_rfc3339(),
+            "type": "attribution",
+            "payload": attribution,
+        }));
+    }
+
+    if let Err(why) = sqlx::query!(
+        r#"UPDATE ttc_events SET payload = payload || $1::text::jsonb WHERE id = $2"#,
+        attribution.to_string(),
+        event_id
+    )
+    .execute(&*data.pool)
+    .await
+    {
+        log::error!(
+            "Unable to add the responsible moderator to an event: {}",
+            why
+        );
+    }
+}
+
+/// Count the stored events of a user in a guild, optionally only of one type and since a point in
+/// time
+pub async fn count_events(
+    pool: &PgPool,
+    guild_id: GuildId,
+    user_id: UserId,
+    event_type: Option<EventType>,
+    since: Option<DateTime<Utc>>,
+) -> Result<i64, Error> {
+    Ok(sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM ttc_events WHERE guild_id = $1 AND user_id = $2
+        AND ($3::varchar IS NULL OR event_type = $3) AND ($4::timestamptz IS NULL OR event_time >= $4)"#,
+        guild_id.0 as i64,
+        user_id.0 as i64,
+        event_type.map(|event_type| event_type.as_str()),
+        since
+    )
+    .fetch_one(pool)
+    .await?
+    .count)
+}
+
+/// Get a page of the stored events of a user in a guild, newest first
+pub async fn fetch_events(
+    pool: &PgPool,
+    guild_id: GuildId,
+    user_id: UserId,
+    event_type: Option<EventType>,
+    since: Option<DateTime<Utc>>,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<StoredEvent>, Error> {
+    let records = sqlx::query!(
+        r#"SELECT event_type, event_time, payload::text AS "payload!" FROM ttc_events WHERE guild_id = $1 AND user_id = $2
+        AND ($3::varchar IS NULL OR event_type = $3) AND ($4::timestamptz IS NULL OR event_time >= $4)
+        ORDER BY event_time DESC, id DESC OFFSET $5 LIMIT $6"#,
+        guild_id.0 as i64,
+        user_id.0 as i64,
+        event_type.map(|event_type| event_type.as_str()),
+        since,
+        offset,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| StoredEvent {
+            event_type: record.event_type,
+            event_time: record.event_time,
+            payload: serde_json::from_str(&record.payload).unwrap_or(Value::Null),
+        })
+        .collect())
+}
+
+/// Split embed fields into pages of at most `max_fields` fields, moving a field to the next page
+/// when its name and value would take the page over `max_chars` characters
+pub fn split_pages(
+    fields: Vec<(String, String)>,
+    max_fields: usize,
+    max_chars: usize,
+) -> Vec<Vec<(String, String)>> {
+    let mut pages = Vec::new();
+    let mut page: Vec<(String, String)> = Vec::new();
+    let mut page_chars = 0;
+    for (name, value) in fields {
+        let chars = name.chars().count() + value.chars().count();
+        if !page.is_empty() && (page.len() >= max_fields || page_chars + chars > max_chars) {
+            pages.push(std::mem::take(&mut page));
+            page_chars = 0;
+        }
+        page_chars += chars;
+        page.push((name, value));
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+/// Format a list of role ids stored in a payload as mentions
+fn role_mentions(roles: &[Value]) -> String {
+    roles
+        .iter()
+        .filter_map(|role| role.as_str())
+        .map(|role| format!("<@&{}>", role))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Remove the events that are older than the configured maximum age, returns how many events were
+/// removed
+async fn clean_event_store(pool: &PgPool, config: &Config) -> Result<u64, Error> {
+    let max_age = match config.event_max_age().await? {
+        Some(max_age) => max_age,
+        None => return Ok(0),
+    };
+    Ok(sqlx::query!(
+        r#"DELETE FROM ttc_events WHERE event_time < $1"#,
+        Utc::now() - chrono::Duration::seconds(max_age)
+    )
+    .execute(pool)
+    .await?
+    .rows_affected())
+}
+
+/// Periodically remove old events from the event store
+pub async fn event_store_cleanup_task(pool: Arc<PgPool>) {
+    let config = Config::new(Arc::clone(&pool));
+    loop {
+        match clean_event_store(&pool, &config).await {
+            Ok(0) => (),
+            Ok(removed) => log::info!("Removed {} old events from the event store", removed),
+            Err(why) => log::error!("Unable to clean the event store: {}", why),
+        }
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
+
+/// Describe a stored event in a few lines for the history
+pub fn describe_event(event_type: &str, payload: &Value) -> String {
+    let text = |key: &str| payload[key].as_str().unwrap_or_default().to_string();
+    let mut lines = Vec::new();
+
+    match EventType::from_db(event_type) {
+        Some(EventType::MessageDelete) => {
+            lines.push(format!(
+                "In <#{}>{}",
+                text("channel_id"),
+                if payload["bulk"].as_bool() == Some(true) {
+                    " (bulk delete)"
+                } else {
+                    ""
+                }
+            ));
+            // The contents are removed from the events of users who deleted their data
+            match payload["content"].as_str() {
+                Some(content) => lines.push(format!(
+                    "Content: {}",
+                    shorten(content, HISTORY_CONTENT_LENGTH)
+                )),
+                None => lines.push("Content: Not available.".to_string()),
+            }
+            if !text("attachments").is_empty() {
+                lines.push(format!(
+                    "Attachments: {}",
+                    shorten(&text("attachments"), HISTORY_CONTENT_LENGTH)
+                ));
+            }
+        }
+        Some(EventType::MessageEdit) => {
+            lines.push(format!("In <#{}>", text("channel_id")));
+            match payload["old_content"].as_str() {
+                Some(old_content) => lines.push(format!(
+                    "Before: {}",
+                    shorten(old_content, HISTORY_CONTENT_LENGTH)
+                )),
+                None => lines.push("Before: Not available.".to_string()),
+            }
+            match payload["new_content"].as_str() {
+                Some(new_content) => lines.push(format!(
+                    "After: {}",
+                    shorten(new_content, HISTORY_CONTENT_LENGTH)
+                )),
+                None => lines.push("After: Not available.".to_string()),
+            }
+        }
+        Some(EventType::MemberJoin) => {
+            if let Some(created_at) = payload["account_created"].as_i64() {
+                lines.push(format!("Account created <t:{}:R>", created_at));
+            }
+        }
+        Some(EventType::MemberLeave) => match payload["joined_at"].as_i64() {
+            Some(joined_at) => lines.push(format!("Joined <t:{}:R>", joined_at)),
+            None => lines.push("Join date not available".to_string()),
+        },
+        Some(EventType::Ban) | Some(EventType::Unban) => (),
+        Some(EventType::MemberUpdate) => {
+            // The old values are missing if the member wasn't cached before the update
+            let old_available = payload["old_roles"].is_array();
+            if old_available && payload["old_nickname"] != payload["new_nickname"] {
+                lines.push(format!(
+                    "Nickname: {} -> {}",
+                    payload["old_nickname"].as_str().unwrap_or("None"),
+                    payload["new_nickname"].as_str().unwrap_or("None")
+                ));
+            }
+            if let (Some(old_roles), Some(new_roles)) = (
+                payload["old_roles"].as_array(),
+                payload["new_roles"].as_array(),
+            ) {
+                let added: Vec<Value> = new_roles
+                    .iter()
+                    .filter(|role| !old_roles.contains(role))
+                    .cloned()
+                    .collect();
+                let removed: Vec<Value> = old_roles
+                    .iter()
+                    .filter(|role| !new_roles.contains(role))
+                    .cloned()
+                    .collect();
+                if !added.is_empty() {
+                    lines.push(format!("Roles added: {}", role_mentions(&added)));
+                }
+                if !removed.is_empty() {
+                    lines.push(format!("Roles removed: {}", role_mentions(&removed)));
+                }
+            }
+            if payload["old_timed_out"] != payload["new_timed_out"] {
+                match payload["new_timed_out"].as_bool() {
+                    Some(true) => lines.push("Timed out".to_string()),
+                    Some(false) => lines.push("Timeout removed".to_string()),
+                    None => (),
+                }
+            }
+        }
+        Some(EventType::ProfileUpdate) => {
+            if payload["old_name"] != payload["new_name"] {
+                lines.push(format!(
+                    "Username: {} -> {}",
+                    text("old_name"),
+                    text("new_name")
+                ));
+            }
+            if payload["old_global_name"] != payload["new_global_name"] {
+                lines.push(format!(
+                    "Display name: {} -> {}",
+                    payload["old_global_name"].as_str().unwrap_or("None"),
+                    payload["new_global_name"].as_str().unwrap_or("None")
+                ));
+            }
+            if payload["old_avatar"] != payload["new_avatar"] {
+                lines.push(format!("Avatar changed: {}", text("new_avatar")));
+            }
+        }
+        None => (),
+    }
+
+    if payload["moderator_id"].is_string() {
+        lines.push(format!(
+            "By <@{}>: {}",
+            text("moderator_id"),
+            payload["reason"].as_str().unwrap_or("No reason given")
+        ));
+    }
+
+    if lines.is_empty() {
+        "No details stored".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_descriptions() {
+        assert_eq!(
+            describe_event(
+                "member_update",
+                &json!({
+                    "old_nickname": null,
+                    "new_nickname": "nyn",
+                    "old_roles": ["1", "2"],
+                    "new_roles": ["2", "3"],
+                    "old_timed_out": false,
+                    "new_timed_out": false,
+                    "moderator_id": "4",
+                    "reason": null,
+                })
+            ),
+            "Nickname: None -> nyn\nRoles added: <@&3>\nRoles removed: <@&1>\nBy <@4>: No reason given"
+        );
+        assert_eq!(
+            describe_event(
+                "message_delete",
+                &json!({"channel_id": "5", "content": "a".repeat(201), "attachments": ""})
+            ),
+            format!("In <#5>\nContent: {}...", "a".repeat(200))
+        );
+        assert_eq!(
+            describe_event("message_delete", &json!({"channel_id": "5"})),
+            "In <#5>\nContent: Not available."
+        );
+        assert_eq!(
+            describe_event(
+                "profile_update",
+                &json!({
+                    "old_name": "nyn",
+                    "new_name": "nyn",
+                    "old_global_name": null,
+                    "new_global_name": "Nyn",
+                    "old_avatar": null,
+                    "new_avatar": null,
+                })
+            ),
+            "Display name: None -> Nyn"
+        );
+        assert_eq!(describe_event("unban", &json!({})), "No details stored");
+    }
+
+    #[test]
+    fn history_pages() {
+        let field = |chars: usize| ("a".to_string(), "b".repeat(chars - 1));
+
+        let pages = split_pages(vec![field(10); 5], 2, 100);
+        assert_eq!(
+            pages.iter().map(|page| page.len()).collect::<Vec<usize>>(),
+            vec![2, 2, 1]
+        );
+
+        // Large fields are moved to the next page before the page gets too long
+        let pages = split_pages(vec![field(40), field(40), field(40), field(10)], 10, 100);
+        assert_eq!(
+            pages.iter().map(|page| page.len()).collect::<Vec<usize>>(),
+            vec![2, 2]
+        );
+
+        assert!(split_pages(Vec::new(), 10, 100).is_empty());
+    }
+}
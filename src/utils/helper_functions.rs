@@ -1,8 +1,12 @@
 use poise::serenity_prelude::{
-    ChannelId, Color, Context, CreateEmbed, Member, Message, Timestamp, Webhook,
+    ChannelId, Color, Context, CreateEmbed, HttpError, Member, Message, SerenityError, Timestamp,
+    Webhook,
 };
 
-use crate::{types::data::Data, Error};
+use crate::{
+    types::{data::Data, log_category::LogCategory},
+    Error,
+};
 use std::time::Duration;
 
 // ----------------
@@ -44,7 +48,7 @@ pub async fn embed_msg(
 #[allow(dead_code)]
 pub async fn alert_mods(ctx: &Context, embed: CreateEmbed, data: &Data) -> Result<(), Error> {
     let mod_role = data.config.moderator_role().await?;
-    for channel in &data.config.conveyance_channel().await? {
+    for channel in &data.config.log_channels(LogCategory::Moderation).await? {
         ChannelId(*channel as u64)
             .send_message(ctx, |m| {
                 m.content(format!("<@&{}>", mod_role))
@@ -96,9 +100,29 @@ pub async fn get_webhook(
     })
 }
 
+// Check if Discord answered that the requested resource doesn't exist (anymore)
+pub fn is_not_found(why: &SerenityError) -> bool {
+    match why {
+        SerenityError::Http(why) => matches!(
+            &**why,
+            HttpError::UnsuccessfulRequest(response) if response.status_code.as_u16() == 404
+        ),
+        _ => false,
+    }
+}
+
 pub fn is_user_timed_out(member: &Member) -> bool {
     match member.communication_disabled_until {
         Some(comm_disabled) => comm_disabled.unix_timestamp() >= Timestamp::now().unix_timestamp(),
         None => false,
     }
 }
+
+// Cut text down to a number of characters, marking that it was shortened
+pub fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max_chars).collect::<String>())
+    }
+}
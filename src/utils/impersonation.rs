@@ -0,0 +1,91 @@
+use poise::serenity_prelude::{Context, GuildId, RoleId, UserId};
+
+/// A staff member whose name a new name is very similar to
+pub struct Impersonation {
+    pub staff_id: UserId,
+    pub staff_name: String,
+    pub name: String,
+    pub similarity: f64,
+}
+
+/// Reduce a name to its lowercase letters and digits, so separators and casing don't hide a copy
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// How similar two names are, from 0 for completely different to 1 for the same
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize_name(a), normalize_name(b));
+    // Names made up only of symbols can't be compared meaningfully
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    strsim::normalized_damerau_levenshtein(&a, &b)
+}
+
+/// Compare the names of a user to the names of the cached staff members and return the most
+/// similar one if it reaches the threshold. Staff members are never checked against each other.
+pub fn find_impersonation(
+    ctx: &Context,
+    guild_id: GuildId,
+    staff_role: RoleId,
+    user_id: UserId,
+    names: &[String],
+    threshold: f64,
+) -> Option<Impersonation> {
+    let staff = ctx.cache.guild_field(guild_id, |guild| {
+        guild
+            .members
+            .values()
+            .filter(|member| member.roles.contains(&staff_role))
+            .map(|member| {
+                let mut staff_names = vec![member.user.name.clone()];
+                staff_names.extend(member.user.global_name.clone());
+                staff_names.extend(member.nick.clone());
+                (member.user.id, staff_names)
+            })
+            .collect::<Vec<(UserId, Vec<String>)>>()
+    })?;
+    if staff.iter().any(|(staff_id, _)| *staff_id == user_id) {
+        return None;
+    }
+
+    let mut best: Option<Impersonation> = None;
+    for (staff_id, staff_names) in staff {
+        for staff_name in &staff_names {
+            for name in names {
+                let similarity = name_similarity(name, staff_name);
+                if similarity >= threshold
+                    && best
+                        .as_ref()
+                        .map_or(true, |best| similarity > best.similarity)
+                {
+                    best = Some(Impersonation {
+                        staff_id,
+                        staff_name: staff_name.clone(),
+                        name: name.clone(),
+                        similarity,
+                    });
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_names() {
+        assert_eq!(name_similarity("Kirottu", "kirottu"), 1.0);
+        assert_eq!(name_similarity("Kir_ottu", "kirottu"), 1.0);
+        assert!(name_similarity("kirotu", "kirottu") > 0.8);
+        assert!(name_similarity("someone", "kirottu") < 0.5);
+        assert_eq!(name_similarity("___", "kirottu"), 0.0);
+    }
+}
@@ -0,0 +1,256 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    types::{config::Config, message_state::MessageState},
+    Error,
+};
+
+// How often old messages are removed from the message cache
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+const EXPORT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S UTC";
+
+/// Remove the cached messages that are older than the configured maximum age and trim the cache
+/// to the configured maximum amount of messages, returns how many messages were removed
+async fn clean_message_cache(pool: &PgPool, config: &Config) -> Result<u64, Error> {
+    let mut removed = 0;
+
+    if let Some(max_age) = config.message_cache_max_age().await? {
+        removed += sqlx::query!(
+            r#"DELETE FROM ttc_message_cache WHERE message_time < $1"#,
+            Utc::now() - chrono::Duration::seconds(max_age)
+        )
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+
+    if let Some(max_rows) = config.message_cache_max_rows().await? {
+        removed += sqlx::query!(
+            r#"DELETE FROM ttc_message_cache WHERE message_id IN
+            (SELECT message_id FROM ttc_message_cache ORDER BY message_time DESC OFFSET $1)"#,
+            max_rows
+        )
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(removed)
+}
+
+/// Periodically remove old messages from the message cache
+pub async fn message_cache_cleanup_task(pool: Arc<PgPool>) {
+    let config = Config::new(Arc::clone(&pool));
+    loop {
+        match clean_message_cache(&pool, &config).await {
+            Ok(0) => (),
+            Ok(removed) => log::info!("Removed {} old messages from the message cache", removed),
+            Err(why) => log::error!("Unable to clean the message cache: {}", why),
+        }
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
+
+/// Mark cached messages as deleted, they are kept until the cleanup removes them
+pub async fn mark_deleted(pool: &PgPool, message_ids: &[i64]) {
+    if let Err(why) = sqlx::query!(
+        r#"UPDATE ttc_message_cache SET deleted_at = $1 WHERE message_id = ANY($2) AND deleted_at IS NULL"#,
+        Utc::now(),
+        message_ids
+    )
+    .execute(pool)
+    .await
+    {
+        log::error!("Error marking messages as deleted in the message cache: {}", why);
+    }
+}
+
+/// The filters of a message cache search, every filter that is set has to match
+#[derive(Default)]
+pub struct MessageFilter {
+    pub user_id: Option<i64>,
+    pub channel_id: Option<i64>,
+    // Case insensitive substring of the content
+    pub content: Option<String>,
+    // Case insensitive PostgreSQL regular expression matched against the content
+    pub pattern: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub state: Option<MessageState>,
+    // The channels the messages can be from, every other channel is left out
+    pub channel_ids: Vec<i64>,
+}
+
+/// A message found by a message cache search
+pub struct SearchedMessage {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub user_id: i64,
+    pub message_time: DateTime<Utc>,
+    pub content: String,
+    pub attachments: String,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Check whether the database accepts a regular expression before searching with it
+pub async fn is_valid_pattern(pool: &PgPool, pattern: &str) -> bool {
+    sqlx::query!(r#"SELECT '' ~* $1 AS "matches""#, pattern)
+        .fetch_one(pool)
+        .await
+        .is_ok()
+}
+
+/// Count the cached messages matching a search
+pub async fn count_messages(pool: &PgPool, filter: &MessageFilter) -> Result<i64, Error> {
+    Ok(sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM ttc_message_cache
+        WHERE ($1::int8 IS NULL OR user_id = $1) AND ($2::int8 IS NULL OR channel_id = $2)
+        AND ($3::text IS NULL OR strpos(lower(content), lower($3)) > 0) AND ($4::text IS NULL OR content ~* $4)
+        AND ($5::timestamptz IS NULL OR message_time >= $5) AND ($6::timestamptz IS NULL OR message_time <= $6)
+        AND (NOT $7 OR deleted_at IS NOT NULL) AND (NOT $8 OR edited_at IS NOT NULL) AND channel_id = ANY($9)"#,
+        filter.user_id,
+        filter.channel_id,
+        filter.content,
+        filter.pattern,
+        filter.since,
+        filter.until,
+        filter.state == Some(MessageState::Deleted),
+        filter.state == Some(MessageState::Edited),
+        &filter.channel_ids
+    )
+    .fetch_one(pool)
+    .await?
+    .count)
+}
+
+/// Get the cached messages matching a search, newest first. Without a limit every match is
+/// returned.
+pub async fn fetch_messages(
+    pool: &PgPool,
+    filter: &MessageFilter,
+    offset: i64,
+    limit: Option<i64>,
+) -> Result<Vec<SearchedMessage>, Error> {
+    Ok(sqlx::query_as!(
+        SearchedMessage,
+        r#"SELECT message_id, channel_id, user_id, message_time, content, attachments, edited_at, deleted_at
+        FROM ttc_message_cache
+        WHERE ($1::int8 IS NULL OR user_id = $1) AND ($2::int8 IS NULL OR channel_id = $2)
+        AND ($3::text IS NULL OR strpos(lower(content), lower($3)) > 0) AND ($4::text IS NULL OR content ~* $4)
+        AND ($5::timestamptz IS NULL OR message_time >= $5) AND ($6::timestamptz IS NULL OR message_time <= $6)
+        AND (NOT $7 OR deleted_at IS NOT NULL) AND (NOT $8 OR edited_at IS NOT NULL) AND channel_id = ANY($9)
+        ORDER BY message_time DESC, message_id DESC OFFSET $10 LIMIT $11"#,
+        filter.user_id,
+        filter.channel_id,
+        filter.content,
+        filter.pattern,
+        filter.since,
+        filter.until,
+        filter.state == Some(MessageState::Deleted),
+        filter.state == Some(MessageState::Edited),
+        &filter.channel_ids,
+        offset,
+        limit
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Write the found messages as plain text, one message per block. Stops before the text would
+/// grow over `max_bytes`, returns the text and how many messages were written.
+pub fn export_text(messages: &[SearchedMessage], max_bytes: usize) -> (String, usize) {
+    let mut text = String::new();
+    for (written, msg) in messages.iter().enumerate() {
+        let mut block = String::new();
+        let mut header = format!(
+            "[{}] channel {} user {} message {}",
+            msg.message_time.format(EXPORT_TIME_FORMAT),
+            msg.channel_id,
+            msg.user_id,
+            msg.message_id
+        );
+        if let Some(edited_at) = msg.edited_at {
+            header.push_str(&format!(
+                " (edited {})",
+                edited_at.format(EXPORT_TIME_FORMAT)
+            ));
+        }
+        if let Some(deleted_at) = msg.deleted_at {
+            header.push_str(&format!(
+                " (deleted {})",
+                deleted_at.format(EXPORT_TIME_FORMAT)
+            ));
+        }
+        block.push_str(&header);
+        block.push('\n');
+        if !msg.content.is_empty() {
+            block.push_str(&msg.content);
+            block.push('\n');
+        }
+        if !msg.attachments.is_empty() {
+            block.push_str(&format!("Attachments: {}\n", msg.attachments));
+        }
+        block.push('\n');
+
+        if text.len() + block.len() > max_bytes {
+            return (text, written);
+        }
+        text.push_str(&block);
+    }
+    (text, messages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn exported_messages() {
+        let message_time = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let messages = [
+            SearchedMessage {
+                message_id: 3,
+                channel_id: 2,
+                user_id: 1,
+                message_time,
+                content: "hello".to_string(),
+                attachments: String::new(),
+                edited_at: None,
+                deleted_at: Some(message_time + chrono::Duration::minutes(1)),
+            },
+            SearchedMessage {
+                message_id: 4,
+                channel_id: 2,
+                user_id: 1,
+                message_time,
+                content: String::new(),
+                attachments: "https://example.com/a.png".to_string(),
+                edited_at: None,
+                deleted_at: None,
+            },
+        ];
+        let first = "[2023-05-01 12:00:00 UTC] channel 2 user 1 message 3 (deleted 2023-05-01 12:01:00 UTC)\n\
+            hello\n\n";
+        assert_eq!(
+            export_text(&messages, usize::MAX),
+            (
+                format!(
+                    "{}[2023-05-01 12:00:00 UTC] channel 2 user 1 message 4\n\
+                    Attachments: https://example.com/a.png\n\n",
+                    first
+                ),
+                2
+            )
+        );
+        // Messages that don't fit anymore are left out as a whole
+        assert_eq!(
+            export_text(&messages, first.len() + 10),
+            (first.to_string(), 1)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::{future::Future, time::Duration};
+
+use futures::StreamExt;
+use poise::serenity_prelude::{CreateEmbed, InteractionResponseType};
+
+use crate::{Context, Error};
+
+// How long the page buttons keep working after the last press
+const PAGINATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Send the first of a number of pages with buttons to switch between them, wrapping around at the
+/// ends. The pages are built when they are switched to, so they can be read from the database as
+/// needed. The buttons are left out if there is only one page and removed once they time out.
+pub async fn paginate<F, Fut>(
+    ctx: Context<'_>,
+    pages: usize,
+    ephemeral: bool,
+    mut get_page: F,
+) -> Result<(), Error>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<CreateEmbed, Error>>,
+{
+    let mut page = 0;
+    let embed = get_page(page).await?;
+
+    // Only add the buttons if there is something to switch between
+    let reply = ctx
+        .send(|m| {
+            m.embed(|e| {
+                e.clone_from(&embed);
+                e
+            })
+            .ephemeral(ephemeral);
+            if pages > 1 {
+                m.components(|c| {
+                    c.create_action_row(|a| {
+                        a.create_button(|b| b.label("Back").custom_id("ttc-page-back"))
+                            .create_button(|b| b.label("Next").custom_id("ttc-page-next"))
+                    })
+                });
+            }
+            m
+        })
+        .await?;
+    if pages <= 1 {
+        return Ok(());
+    }
+    let message = reply.message().await?.into_owned();
+
+    // Listen for the interactions
+    while let Some(interaction) = message
+        .await_component_interactions(ctx)
+        .timeout(PAGINATION_TIMEOUT)
+        .author_id(ctx.author().id)
+        .build()
+        .next()
+        .await
+    {
+        // Change the page depending on the button pressed
+        match interaction.data.custom_id.as_str() {
+            "ttc-page-back" => page = (page + pages - 1) % pages,
+            "ttc-page-next" => page = (page + 1) % pages,
+            _ => unreachable!(),
+        }
+        let embed = get_page(page).await?;
+        interaction
+            .create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| d.set_embed(embed))
+            })
+            .await?;
+    }
+    // Remove the buttons when we are no longer listening for events
+    reply.edit(ctx, |m| m.components(|c| c)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,354 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{
+    ChannelId, Context, CreateEmbed, CreateSelectMenu, Emoji, GuildId, MessageId, Role, RoleId,
+};
+use sqlx::{postgres::PgListener, PgPool};
+
+use crate::{
+    types::{colors::Colors, config::Config},
+    unwrap_or_return,
+    utils::helper_functions::is_not_found,
+    Error,
+};
+
+/// The Postgres channel the database notifies on changes to the self roles
+const SELFROLES_CHANGED_CHANNEL: &str = "ttc_selfroles_changed";
+
+/// A self role as it is stored in the database: role, emoji, group and required role
+pub type Selfrole = (i64, Option<String>, Option<i32>, Option<i64>);
+
+/// A self role menu ready to be sent, `group_id` is `None` for the menu of the roles without a group
+pub struct SelfroleMenu {
+    pub group_id: Option<i32>,
+    pub embed: CreateEmbed,
+    pub menu: CreateSelectMenu,
+}
+
+/// The self role menus built from the database and the problems found while building them
+pub struct BuiltMenus {
+    pub menus: Vec<SelfroleMenu>,
+    // Roles that don't exist on the server anymore
+    pub stale_roles: Vec<i64>,
+    // Groups that couldn't be turned into a menu and why
+    pub skipped_groups: Vec<(Option<i32>, String)>,
+}
+
+/// What happened while refreshing the posted self role menus
+#[derive(Default)]
+pub struct RefreshReport {
+    pub updated: usize,
+    pub removed: usize,
+    pub stale_roles: Vec<i64>,
+    pub skipped_groups: Vec<String>,
+}
+
+impl RefreshReport {
+    /// Log the problems with the self roles that were found during the refresh
+    pub fn log_problems(&self) {
+        if !self.stale_roles.is_empty() {
+            log::warn!(
+                "Self roles reference roles that don't exist anymore: {:?}",
+                self.stale_roles
+            );
+        }
+        for problem in &self.skipped_groups {
+            log::warn!("Self role group left out: {}", problem);
+        }
+    }
+}
+
+/// Build the self role menus from the database, either for every group or only a single one.
+/// Roles that don't exist on the server anymore and groups that can't be shown are left out and
+/// returned separately.
+pub async fn build_selfrole_menus(
+    ctx: &Context,
+    guild_id: GuildId,
+    config: &Config,
+    colors: &Colors,
+    group: Option<i32>,
+) -> Result<BuiltMenus, Error> {
+    let raw_selfroles = config.selfroles().await?;
+    let groups = config.selfrole_groups().await?;
+
+    let role_hmap = guild_id.roles(ctx).await?;
+    let emojis = guild_id.emojis(ctx).await?;
+
+    let mut emoji_hmap = HashMap::new();
+    for emoji in &emojis {
+        emoji_hmap.insert(emoji.name.clone(), emoji.clone());
+    }
+
+    // Roles without a group keep using the old menu
+    let mut sections = vec![(None, "Manage your self roles here".to_string(), None, false)];
+    sections.extend(
+        groups
+            .into_iter()
+            .map(|(id, name, description, exclusive)| (Some(id), name, description, exclusive)),
+    );
+
+    let color = colors.selfrole_selection().await;
+    let mut menus = Vec::new();
+    let mut stale_roles = Vec::new();
+    let mut skipped_groups = Vec::new();
+    for (group_id, name, description, exclusive) in sections {
+        if group.is_some() && group != group_id {
+            continue;
+        }
+
+        let selfroles: Vec<&Selfrole> = raw_selfroles.iter().filter(|r| r.2 == group_id).collect();
+        let custom_id = match group_id {
+            Some(group_id) => format!("ttc-bot-self-role-menu-{}", group_id),
+            None => "ttc-bot-self-role-menu".to_string(),
+        };
+        let menu = match selfrole_menu(
+            custom_id,
+            &selfroles,
+            exclusive,
+            &role_hmap,
+            &emoji_hmap,
+            &mut stale_roles,
+        ) {
+            Ok(Some(menu)) => menu,
+            Ok(None) => continue,
+            // One broken group shouldn't keep the others from being shown
+            Err(why) => {
+                skipped_groups.push((group_id, format!("{}: {}", name, why)));
+                continue;
+            }
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed.title(name).color(color);
+        if let Some(description) = description {
+            embed.description(description);
+        }
+        if exclusive {
+            embed.footer(|f| f.text("You can only pick one of these roles"));
+        }
+
+        menus.push(SelfroleMenu {
+            group_id,
+            embed,
+            menu,
+        });
+    }
+
+    stale_roles.sort_unstable();
+    stale_roles.dedup();
+    Ok(BuiltMenus {
+        menus,
+        stale_roles,
+        skipped_groups,
+    })
+}
+
+/// Create the selection menu for a list of self roles, returns `None` if none of the roles exist
+fn selfrole_menu(
+    custom_id: String,
+    selfroles: &[&Selfrole],
+    exclusive: bool,
+    role_hmap: &HashMap<RoleId, Role>,
+    emoji_hmap: &HashMap<String, Emoji>,
+    stale_roles: &mut Vec<i64>,
+) -> Result<Option<CreateSelectMenu>, Error> {
+    let mut option_data: Vec<(Role, Option<&Emoji>, Option<&Role>)> = Vec::new();
+    for val in selfroles {
+        let role = match role_hmap.get(&RoleId(val.0 as u64)) {
+            Some(role) => role,
+            None => {
+                stale_roles.push(val.0);
+                continue;
+            }
+        };
+        let emoji = emoji_hmap.get(val.1.as_deref().unwrap_or(""));
+        let required_role = match val.3 {
+            Some(required_role) => match role_hmap.get(&RoleId(required_role as u64)) {
+                Some(required_role) => Some(required_role),
+                None => {
+                    stale_roles.push(required_role);
+                    None
+                }
+            },
+            None => None,
+        };
+        option_data.push((role.clone(), emoji, required_role));
+    }
+
+    if option_data.is_empty() {
+        return Ok(None);
+    }
+
+    // Discord doesn't allow more options on a single menu
+    if option_data.len() > 25 {
+        return Err(Error::from(format!(
+            "Too many roles for one menu ({}), the limit is 25",
+            option_data.len()
+        )));
+    }
+
+    // Create the selection menu
+    let mut menu = CreateSelectMenu::default();
+    menu.custom_id(custom_id);
+
+    // Set the menu values properly
+    menu.min_values(0);
+    menu.max_values(if exclusive {
+        1
+    } else {
+        option_data.len() as u64
+    });
+
+    // Create the options for the roles
+    menu.options(|m| {
+        for (role, emoji, required_role) in option_data {
+            m.create_option(|o| {
+                o.label(role.name).value(role.id);
+                if let Some(emoji) = emoji {
+                    o.emoji(emoji.clone());
+                }
+                if let Some(required_role) = required_role {
+                    o.description(format!("Requires {}", required_role.name));
+                }
+                o
+            });
+        }
+        m
+    });
+
+    Ok(Some(menu))
+}
+
+/// Edit every posted self role menu of a guild to match the database
+pub async fn refresh_selfrole_menus(
+    ctx: &Context,
+    guild_id: GuildId,
+    pool: &PgPool,
+    config: &Config,
+    colors: &Colors,
+) -> Result<RefreshReport, Error> {
+    let built = build_selfrole_menus(ctx, guild_id, config, colors, None).await?;
+    let mut report = RefreshReport {
+        stale_roles: built.stale_roles,
+        skipped_groups: built
+            .skipped_groups
+            .iter()
+            .map(|(_, problem)| problem.clone())
+            .collect(),
+        ..Default::default()
+    };
+
+    let messages = sqlx::query!(
+        r#"SELECT * FROM ttc_selfrole_messages WHERE guild_id = $1"#,
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let empty_color = colors.input_warn().await;
+    for message in messages {
+        // The posted menu of a skipped group is left as it is until the group is fixed
+        if built
+            .skipped_groups
+            .iter()
+            .any(|(group_id, _)| *group_id == message.group_id)
+        {
+            continue;
+        }
+
+        let menu = built
+            .menus
+            .iter()
+            .find(|menu| menu.group_id == message.group_id);
+        let result = ChannelId(message.channel_id as u64)
+            .edit_message(ctx, MessageId(message.message_id as u64), |m| match menu {
+                Some(menu) => {
+                    let select_menu = menu.menu.clone();
+                    m.set_embed(menu.embed.clone())
+                        .components(|c| c.create_action_row(|a| a.add_select_menu(select_menu)))
+                }
+                // The group is gone or has no roles left
+                None => m
+                    .embed(|e| e.title("No self roles available").color(empty_color))
+                    .components(|c| c),
+            })
+            .await;
+
+        match result {
+            Ok(_) => report.updated += 1,
+            Err(why) if is_not_found(&why) => {
+                // Someone deleted the message, so there is nothing to keep up to date anymore
+                sqlx::query!(
+                    r#"DELETE FROM ttc_selfrole_messages WHERE message_id = $1"#,
+                    message.message_id
+                )
+                .execute(pool)
+                .await?;
+                report.removed += 1;
+            }
+            Err(why) => {
+                log::warn!(
+                    "Unable to refresh self role menu {}: {}",
+                    message.message_id,
+                    why
+                );
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Refresh the self role menus of every guild that has some, logging the outcome
+pub async fn refresh_all_selfrole_menus(
+    ctx: &Context,
+    pool: &PgPool,
+    config: &Config,
+    colors: &Colors,
+) -> Result<(), Error> {
+    let guilds = sqlx::query!(r#"SELECT DISTINCT guild_id FROM ttc_selfrole_messages"#)
+        .fetch_all(pool)
+        .await?;
+
+    for guild in guilds {
+        let report =
+            refresh_selfrole_menus(ctx, GuildId(guild.guild_id as u64), pool, config, colors)
+                .await?;
+        log::info!(
+            "Refreshed {} self role menus, {} deleted",
+            report.updated,
+            report.removed
+        );
+        report.log_problems();
+    }
+
+    Ok(())
+}
+
+/// Listen for changes to the self roles in the database and refresh the menus when they happen
+pub async fn selfrole_change_listener(ctx: Context, pool: Arc<PgPool>) {
+    let mut listener = unwrap_or_return!(
+        PgListener::connect_with(&pool).await,
+        "Unable to connect the self role listener"
+    );
+    unwrap_or_return!(
+        listener.listen(SELFROLES_CHANGED_CHANNEL).await,
+        "Unable to listen for self role changes"
+    );
+
+    let config = Config::new(Arc::clone(&pool));
+    let colors = Colors::new(Arc::clone(&pool));
+    loop {
+        match listener.recv().await {
+            Ok(_) => {
+                if let Err(why) = refresh_all_selfrole_menus(&ctx, &pool, &config, &colors).await {
+                    log::error!("Unable to refresh self role menus: {}", why);
+                }
+            }
+            Err(why) => {
+                log::error!("Error receiving self role change: {}", why);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use poise::serenity_prelude::{ChannelId, Color, Context, RoleId, UserId};
+use sqlx::PgPool;
+
+use crate::{
+    types::{colors::Colors, config::Config, log_category::LogCategory},
+    utils::helper_functions::is_not_found,
+    Error,
+};
+
+// How often the database is checked for expired temporary roles
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Log a grant or removal of a temporary role to the moderation logs
+pub async fn log_temp_role(
+    ctx: &Context,
+    config: &Config,
+    title: &str,
+    description: &str,
+    user_id: UserId,
+    role_id: RoleId,
+    color: Color,
+) -> Result<(), Error> {
+    for channel in &config.log_channels(LogCategory::Moderation).await? {
+        ChannelId(*channel as u64)
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title(title)
+                        .description(description)
+                        .field("User", format!("<@{}>", user_id), true)
+                        .field("UserID", user_id, true)
+                        .field("Role", format!("<@&{}>", role_id), true)
+                        .color(color)
+                        .timestamp(Utc::now())
+                })
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Remove every temporary role that has expired, including the ones that expired while the bot
+/// was offline
+async fn remove_expired_temp_roles(
+    ctx: &Context,
+    pool: &PgPool,
+    config: &Config,
+    colors: &Colors,
+) -> Result<(), Error> {
+    let expired = sqlx::query!(
+        r#"SELECT * FROM ttc_temp_roles WHERE expires_at <= $1"#,
+        Utc::now()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for temp_role in expired {
+        let user_id = UserId(temp_role.user_id as u64);
+        let role_id = RoleId(temp_role.role_id as u64);
+
+        let description = match ctx
+            .http
+            .remove_member_role(
+                temp_role.guild_id as u64,
+                user_id.0,
+                role_id.0,
+                Some("Temporary role expired"),
+            )
+            .await
+        {
+            Ok(_) => "The temporary role has expired and was removed.".to_string(),
+            // The member left or the role was deleted, so there is nothing left to remove
+            Err(why) if is_not_found(&why) => {
+                "The temporary role has expired, the member or role doesn't exist anymore."
+                    .to_string()
+            }
+            // Try again on the next check
+            Err(why) => {
+                log::warn!(
+                    "Unable to remove temporary role {} from {}: {}",
+                    role_id,
+                    user_id,
+                    why
+                );
+                continue;
+            }
+        };
+
+        sqlx::query!(r#"DELETE FROM ttc_temp_roles WHERE id = $1"#, temp_role.id)
+            .execute(pool)
+            .await?;
+        // Members that left shouldn't get the role back when they rejoin
+        sqlx::query!(
+            r#"DELETE FROM ttc_role_memory WHERE user_id = $1 AND role_id = $2"#,
+            temp_role.user_id,
+            temp_role.role_id
+        )
+        .execute(pool)
+        .await?;
+
+        log_temp_role(
+            ctx,
+            config,
+            "Temporary role removed",
+            &description,
+            user_id,
+            role_id,
+            colors.conveyance_temp_role_removed().await,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Periodically remove the temporary roles that have expired
+pub async fn temp_role_expiry_task(ctx: Context, pool: Arc<PgPool>) {
+    let config = Config::new(Arc::clone(&pool));
+    let colors = Colors::new(Arc::clone(&pool));
+    loop {
+        if let Err(why) = remove_expired_temp_roles(&ctx, &pool, &config, &colors).await {
+            log::error!("Unable to remove expired temporary roles: {}", why);
+        }
+        tokio::time::sleep(EXPIRY_CHECK_INTERVAL).await;
+    }
+}
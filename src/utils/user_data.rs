@@ -0,0 +1,351 @@
+use chrono::Utc;
+use poise::serenity_prelude::UserId;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{utils::attachment_archive, Error};
+
+// What the title of an anonymised support ticket is replaced with
+const REMOVED_TITLE: &str = "[removed]";
+
+/// How many rows were removed or anonymised when deleting the data of a user
+pub struct DeletedData {
+    pub messages: u64,
+    pub archived_attachments: u64,
+    pub emoji_stats: u64,
+    pub onboarding_answers: u64,
+    pub invite_joins: u64,
+    pub support_tickets: u64,
+    pub event_contents: u64,
+}
+
+impl DeletedData {
+    pub fn total(&self) -> u64 {
+        self.messages
+            + self.archived_attachments
+            + self.emoji_stats
+            + self.onboarding_answers
+            + self.invite_joins
+            + self.support_tickets
+            + self.event_contents
+    }
+}
+
+/// Collect everything stored about a user into a single JSON document. Ids are written as strings,
+/// since they don't fit into the numbers of many JSON readers.
+pub async fn export_user_data(pool: &PgPool, user_id: UserId) -> Result<Value, Error> {
+    let user_id = user_id.0 as i64;
+
+    let tickets = sqlx::query!(
+        r#"SELECT incident_id, thread_id, incident_time, incident_title, incident_description, incident_system_info, incident_solved FROM ttc_support_tickets
+        WHERE user_id = $1 ORDER BY incident_time"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|ticket| {
+        json!({
+            "incident_id": ticket.incident_id,
+            "thread_id": ticket.thread_id.to_string(),
+            "created_at": ticket.incident_time.to_rfc3339(),
+            "title": ticket.incident_title,
+            "description": ticket.incident_description,
+            "system_info": ticket.incident_system_info,
+            "solved": ticket.incident_solved,
+        })
+    })
+    .collect::<Vec<Value>>();
+    // The tickets of other users are only referenced, their contents belong to the ticket creator
+    let assigned_tickets = sqlx::query!(
+        r#"SELECT incident_id, thread_id, incident_time, incident_solved FROM ttc_support_tickets
+        WHERE assignee_id = $1 ORDER BY incident_time"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|ticket| {
+        json!({
+            "incident_id": ticket.incident_id,
+            "thread_id": ticket.thread_id.to_string(),
+            "created_at": ticket.incident_time.to_rfc3339(),
+            "solved": ticket.incident_solved,
+        })
+    })
+    .collect::<Vec<Value>>();
+
+    let messages = sqlx::query!(
+        r#"SELECT message_id, channel_id, message_time, content, attachments, edited_at, deleted_at FROM ttc_message_cache
+        WHERE user_id = $1 ORDER BY message_time"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|msg| {
+        json!({
+            "message_id": msg.message_id.to_string(),
+            "channel_id": msg.channel_id.to_string(),
+            "sent_at": msg.message_time.to_rfc3339(),
+            "content": msg.content,
+            "attachments": msg.attachments,
+            "edited_at": msg.edited_at.map(|time| time.to_rfc3339()),
+            "deleted_at": msg.deleted_at.map(|time| time.to_rfc3339()),
+        })
+    })
+    .collect::<Vec<Value>>();
+
+    // The archived files themselves are not included, only what is known about them
+    let archived_attachments = sqlx::query!(
+        r#"SELECT tac.message_id, tac.filename, tac."size", tac.archived_at FROM ttc_attachment_archive tac
+        INNER JOIN ttc_message_cache tmc ON tac.message_id = tmc.message_id
+        WHERE tmc.user_id = $1 ORDER BY tac.archived_at, tac.id"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|attachment| {
+        json!({
+            "message_id": attachment.message_id.to_string(),
+            "filename": attachment.filename,
+            "size": attachment.size,
+            "archived_at": attachment.archived_at.to_rfc3339(),
+        })
+    })
+    .collect::<Vec<Value>>();
+
+    let emojis = sqlx::query!(
+        r#"SELECT emoji_name, emoji_count FROM ttc_emoji_cache WHERE user_id = $1 ORDER BY emoji_count DESC"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|emoji| json!({ "emoji": emoji.emoji_name, "count": emoji.emoji_count }))
+    .collect::<Vec<Value>>();
+    let counted_messages = sqlx::query!(
+        r#"SELECT num_messages FROM ttc_emoji_cache_messages WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|record| record.num_messages);
+
+    let onboarding_answers = sqlx::query!(
+        r#"SELECT question, answer, answer_time FROM ttc_onboarding_answers WHERE user_id = $1 ORDER BY answer_time"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|answer| {
+        json!({
+            "question": answer.question,
+            "answer": answer.answer,
+            "answered_at": answer.answer_time.to_rfc3339(),
+        })
+    })
+    .collect::<Vec<Value>>();
+
+    let invite_joins = sqlx::query!(
+        r#"SELECT guild_id, invite_code, joined_at, left_at FROM ttc_invite_joins WHERE user_id = $1 ORDER BY joined_at"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|join| {
+        json!({
+            "guild_id": join.guild_id.to_string(),
+            "invite_code": join.invite_code,
+            "joined_at": join.joined_at.to_rfc3339(),
+            "left_at": join.left_at.map(|time| time.to_rfc3339()),
+        })
+    })
+    .collect::<Vec<Value>>();
+
+    // The moderation records are part of the export, but they are kept when deleting
+    let events = sqlx::query!(
+        r#"SELECT event_type, event_time, payload::text AS "payload!" FROM ttc_events WHERE user_id = $1 ORDER BY event_time"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|event| {
+        json!({
+            "type": event.event_type,
+            "time": event.event_time.to_rfc3339(),
+            "details": serde_json::from_str::<Value>(&event.payload).unwrap_or(Value::Null),
+        })
+    })
+    .collect::<Vec<Value>>();
+    let temp_roles = sqlx::query!(
+        r#"SELECT role_id, granted_by, expires_at FROM ttc_temp_roles WHERE user_id = $1 ORDER BY expires_at"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|temp_role| {
+        json!({
+            "role_id": temp_role.role_id.to_string(),
+            "granted_by": temp_role.granted_by.to_string(),
+            "expires_at": temp_role.expires_at.to_rfc3339(),
+        })
+    })
+    .collect::<Vec<Value>>();
+    let remembered_roles = sqlx::query!(
+        r#"SELECT guild_id, role_id, left_at FROM ttc_role_memory WHERE user_id = $1 ORDER BY left_at"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|role| {
+        json!({
+            "guild_id": role.guild_id.to_string(),
+            "role_id": role.role_id.to_string(),
+            "left_at": role.left_at.to_rfc3339(),
+        })
+    })
+    .collect::<Vec<Value>>();
+    let verification_request = sqlx::query!(
+        r#"SELECT requested_at, approved_at, denied_at FROM ttc_verification_requests WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|request| {
+        json!({
+            "requested_at": request.requested_at.to_rfc3339(),
+            "approved_at": request.approved_at.map(|time| time.to_rfc3339()),
+            "denied_at": request.denied_at.map(|time| time.to_rfc3339()),
+        })
+    });
+
+    Ok(json!({
+        "user_id": user_id.to_string(),
+        "exported_at": Utc::now().to_rfc3339(),
+        "support_tickets": {
+            "created": tickets,
+            "assigned": assigned_tickets,
+        },
+        "cached_messages": messages,
+        "archived_attachments": archived_attachments,
+        "emoji_usage": {
+            "counted_messages": counted_messages,
+            "emojis": emojis,
+        },
+        "onboarding_answers": onboarding_answers,
+        "invite_joins": invite_joins,
+        "moderation": {
+            "events": events,
+            "temporary_roles": temp_roles,
+            "remembered_roles": remembered_roles,
+            "verification_request": verification_request,
+        },
+    }))
+}
+
+/// Remove the data of a user that isn't needed for moderation, including the archived attachments
+/// of their cached messages. Support tickets are anonymised
+/// instead of removed, so the ticket numbers and threads stay consistent. The event store,
+/// temporary roles and remembered roles are kept as moderation records, but the message contents
+/// and attachments are removed from the stored events.
+pub async fn delete_user_data(pool: &PgPool, user_id: UserId) -> Result<DeletedData, Error> {
+    let user_id = user_id.0 as i64;
+
+    // The archive is only linked to the user through the cached messages, so the archived files
+    // have to go before the messages do
+    let message_ids = sqlx::query!(
+        r#"SELECT message_id FROM ttc_message_cache WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|record| record.message_id)
+    .collect::<Vec<i64>>();
+    let archived_attachments =
+        attachment_archive::remove_archived_attachments(pool, &message_ids).await?;
+
+    let mut transaction = pool.begin().await?;
+
+    let messages = sqlx::query!(
+        r#"DELETE FROM ttc_message_cache WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let emoji_stats = sqlx::query!(r#"DELETE FROM ttc_emoji_cache WHERE user_id = $1"#, user_id)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+        + sqlx::query!(
+            r#"DELETE FROM ttc_emoji_cache_messages WHERE user_id = $1"#,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+
+    let onboarding_answers = sqlx::query!(
+        r#"DELETE FROM ttc_onboarding_answers WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    // The members they invited keep their joins, only without the inviter
+    let invite_joins = sqlx::query!(
+        r#"DELETE FROM ttc_invite_joins WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected()
+        + sqlx::query!(
+            r#"UPDATE ttc_invite_joins SET inviter_id = NULL WHERE inviter_id = $1"#,
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+
+    let support_tickets = sqlx::query!(
+        r#"UPDATE ttc_support_tickets SET user_id = 0, incident_title = $1, incident_description = NULL, incident_system_info = NULL
+        WHERE user_id = $2"#,
+        REMOVED_TITLE,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let event_contents = sqlx::query!(
+        r#"UPDATE ttc_events SET payload = payload - 'content' - 'old_content' - 'new_content' - 'attachments'
+        WHERE user_id = $1 AND payload ?| array['content', 'old_content', 'new_content', 'attachments']"#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    transaction.commit().await?;
+
+    Ok(DeletedData {
+        messages,
+        archived_attachments,
+        emoji_stats,
+        onboarding_answers,
+        invite_joins,
+        support_tickets,
+        event_contents,
+    })
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{User, UserId};
+use ril::prelude::*;
+use std::{fs, io::Cursor};
+
+use crate::{traits::readable::Readable, utils::userinfo::get_basepath, Error};
+
+const CARD_WIDTH: u32 = 800;
+const CARD_HEIGHT: u32 = 250;
+const CARD_AVATAR_SIZE: u32 = 180;
+const CARD_PADDING: u32 = 35;
+const CARD_NAME_FONT_SIZE: f32 = 42.0;
+const CARD_NUMBER_FONT_SIZE: f32 = 28.0;
+// Longer names would run off the card
+const CARD_MAX_NAME_LENGTH: usize = 20;
+
+/// Everything that can be filled into a welcome message
+pub struct WelcomeInfo {
+    pub mention: String,
+    pub username: String,
+    pub server: String,
+    pub member_count: u64,
+    pub account_age: String,
+}
+
+/// Replace the placeholders of a welcome message with the info about the new member
+pub fn fill_welcome_template(template: &str, info: &WelcomeInfo) -> String {
+    template
+        .replace("%user%", &info.mention)
+        .replace("%username%", &info.username)
+        .replace("%server%", &info.server)
+        .replace("%membercount%", &info.member_count.to_string())
+        .replace("%account_age%", &info.account_age)
+}
+
+/// Get the age of an account in whole days, or hours for accounts younger than a day
+pub fn account_age(created_at: DateTime<Utc>) -> String {
+    let age = Utc::now() - created_at;
+    let age = match age.num_days() {
+        0 => chrono::Duration::hours(age.num_hours()),
+        days => chrono::Duration::days(days),
+    };
+    age.readable().trim_end().to_string()
+}
+
+/// Get the file name of the welcome card of a user
+pub fn get_welcome_card_filename(user_id: UserId) -> String {
+    format!("welcome-{}.png", user_id.0)
+}
+
+/// Remove a welcome card after it has been sent
+pub fn remove_welcome_card(path: &str) {
+    if let Err(why) = fs::remove_file(path) {
+        log::warn!("Unable to remove welcome card {}: {}", path, why);
+    }
+}
+
+/// Download the avatar of the user and render their welcome card, returns the path of the image
+pub async fn create_welcome_card(user: &User, member_number: u64) -> Result<String, Error> {
+    let basepath = get_basepath()?;
+    fs::create_dir_all(basepath.clone())?;
+
+    // Always ask for a png, the default webp can't be decoded
+    let url = match &user.avatar {
+        Some(hash) => format!(
+            "https://cdn.discordapp.com/avatars/{}/{}.png?size=256",
+            user.id.0, hash
+        ),
+        None => user.default_avatar_url(),
+    };
+    let mut avatar_path = basepath.clone();
+    avatar_path.push(format!("avatar-{}.png", user.id.0));
+    let avatar_path = avatar_path.to_str().unwrap().to_string();
+
+    let response = reqwest::get(&url).await?;
+    let mut file = fs::File::create(&avatar_path)?;
+    let mut content = Cursor::new(response.bytes().await?);
+    std::io::copy(&mut content, &mut file)?;
+
+    let mut card_path = basepath;
+    card_path.push(get_welcome_card_filename(user.id));
+    let card_path = card_path.to_str().unwrap().to_string();
+
+    let result = render_welcome_card(&avatar_path, &user.name, member_number, &card_path);
+    if let Err(why) = fs::remove_file(&avatar_path) {
+        log::warn!("Unable to remove avatar {}: {}", avatar_path, why);
+    }
+    result.map(|_| card_path)
+}
+
+/// Render the welcome card from the downloaded avatar
+fn render_welcome_card(
+    avatar_path: &str,
+    name: &str,
+    member_number: u64,
+    output_path: &str,
+) -> Result<(), Error> {
+    let mut cvs = Image::new(CARD_WIDTH, CARD_HEIGHT, Rgba::new(32, 34, 37, 255));
+
+    let mut avatar: Image<Rgba> = match Image::open(avatar_path) {
+        Ok(avatar) => avatar,
+        Err(why) => return Err(format!("Avatar could not be loaded: {}", why).into()),
+    };
+    avatar.resize(
+        CARD_AVATAR_SIZE,
+        CARD_AVATAR_SIZE,
+        ResizeAlgorithm::Lanczos3,
+    );
+
+    // Cut the avatar into a circle
+    let radius = CARD_AVATAR_SIZE as f64 / 2.0;
+    for x in 0..CARD_AVATAR_SIZE {
+        for y in 0..CARD_AVATAR_SIZE {
+            let dx = x as f64 + 0.5 - radius;
+            let dy = y as f64 + 0.5 - radius;
+            if dx * dx + dy * dy > radius * radius {
+                avatar.set_pixel(x, y, Rgba::transparent());
+            }
+        }
+    }
+    cvs.paste(CARD_PADDING, CARD_PADDING, &avatar);
+
+    let font_bytes = include_bytes!("../../res/DejaVuSans.ttf");
+    let (name_font, number_font) = match (
+        Font::from_bytes(font_bytes, CARD_NAME_FONT_SIZE),
+        Font::from_bytes(font_bytes, CARD_NUMBER_FONT_SIZE),
+    ) {
+        (Ok(name_font), Ok(number_font)) => (name_font, number_font),
+        _ => return Err(Error::from("Font could not be loaded for the welcome card")),
+    };
+
+    let name = if name.chars().count() > CARD_MAX_NAME_LENGTH {
+        format!(
+            "{}...",
+            name.chars().take(CARD_MAX_NAME_LENGTH).collect::<String>()
+        )
+    } else {
+        name.to_string()
+    };
+    let title = format!("Welcome, {}!", name);
+
+    let text_x = CARD_PADDING * 2 + CARD_AVATAR_SIZE;
+    cvs.draw(&TextSegment::new(&name_font, &title, Rgba::white()).with_position(text_x, 70));
+    cvs.draw(
+        &TextSegment::new(
+            &number_font,
+            &format!("Member #{}", member_number),
+            Rgba::new(185, 187, 190, 255),
+        )
+        .with_position(text_x, 140),
+    );
+
+    match cvs.save_inferred(output_path) {
+        Ok(_) => Ok(()),
+        Err(why) => Err(format!("Couldn't save welcome card to filesystem: {}", why).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn welcome_template() {
+        let info = WelcomeInfo {
+            mention: "<@1234>".to_string(),
+            username: "tux".to_string(),
+            server: "The Tech Corner".to_string(),
+            member_count: 42,
+            account_age: "3 days".to_string(),
+        };
+        assert_eq!(
+            fill_welcome_template(
+                "Hey %user% (%username%), welcome to %server%! You are member %membercount%, your account is %account_age% old.",
+                &info
+            ),
+            "Hey <@1234> (tux), welcome to The Tech Corner! You are member 42, your account is 3 days old."
+        );
+        assert_eq!(
+            fill_welcome_template("No placeholders", &info),
+            "No placeholders"
+        );
+    }
+}